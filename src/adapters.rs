@@ -0,0 +1,304 @@
+use std::fmt;
+use std::str::FromStr;
+
+use walrus::ir::{BinaryOp, UnaryOp, Value};
+use walrus::{FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, ValType};
+
+use crate::error::Wasi2icError;
+
+/// Rule allowing the adapter to bridge a specific difference between the import and the replacement signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterRule {
+    /// Widen i32 to i64 (zero-extended) or narrow i64 to i32 (wrapped) for parameters and results.
+    ConvertIntegers,
+
+    /// Split an i64 import parameter into two i32 replacement parameters (low and high half).
+    SplitI64,
+
+    /// Drop the trailing replacement results not present in the import signature.
+    DropResults,
+
+    /// Supply constant values for the trailing replacement parameters not present in the import signature.
+    ConstParams(Vec<i64>),
+}
+
+impl fmt::Display for AdapterRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdapterRule::ConvertIntegers => write!(f, "integers"),
+            AdapterRule::SplitI64 => write!(f, "split-i64"),
+            AdapterRule::DropResults => write!(f, "drop-results"),
+            AdapterRule::ConstParams(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "const-params:{}", values.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for AdapterRule {
+    type Err = Wasi2icError;
+
+    /// Parse the adapter rule: `integers`, `split-i64`, `drop-results` or `const-params:<value>[,<value>...]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "integers" => Ok(AdapterRule::ConvertIntegers),
+            "split-i64" => Ok(AdapterRule::SplitI64),
+            "drop-results" => Ok(AdapterRule::DropResults),
+            _ => match s.strip_prefix("const-params:") {
+                Some(values) => {
+                    let values = values
+                        .split(',')
+                        .map(|v| {
+                            v.trim()
+                                .parse::<i64>()
+                                .map_err(|_| {
+                                    Wasi2icError::InvalidOption(format!(
+                                        "Invalid constant parameter '{v}'"
+                                    ))
+                                })
+                        })
+                        .collect::<Result<Vec<i64>, Wasi2icError>>()?;
+
+                    Ok(AdapterRule::ConstParams(values))
+                }
+                None => Err(Wasi2icError::InvalidOption(format!(
+                    "Unknown adapter rule '{s}', expected one of: integers, split-i64, drop-results, const-params:<values>"
+                ))),
+            },
+        }
+    }
+}
+
+fn const_value(ty: ValType, value: i64) -> Option<Value> {
+    match ty {
+        ValType::I32 => Some(Value::I32(value as i32)),
+        ValType::I64 => Some(Value::I64(value)),
+        ValType::F32 => Some(Value::F32(value as f32)),
+        ValType::F64 => Some(Value::F64(value as f64)),
+        ValType::V128 | ValType::Ref(_) => None,
+    }
+}
+
+/// Conversion of a value between the import and the replacement types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+    /// The types are the same.
+    Keep,
+    /// Zero-extend i32 to i64.
+    Extend,
+    /// Wrap i64 to i32.
+    Wrap,
+}
+
+/// returns the conversion of the value of type `from` into the type `to`, None if it is not allowed by the rules
+fn conversion(from: ValType, to: ValType, rules: &[AdapterRule]) -> Option<Conversion> {
+    if from == to {
+        return Some(Conversion::Keep);
+    }
+
+    if !rules.contains(&AdapterRule::ConvertIntegers) {
+        return None;
+    }
+
+    match (from, to) {
+        (ValType::I32, ValType::I64) => Some(Conversion::Extend),
+        (ValType::I64, ValType::I32) => Some(Conversion::Wrap),
+        _ => None,
+    }
+}
+
+/// Push the instruction converting the value on the stack.
+fn convert_value(body: &mut InstrSeqBuilder, conversion: Conversion) {
+    match conversion {
+        Conversion::Keep => {}
+        Conversion::Extend => {
+            body.unop(UnaryOp::I64ExtendUI32);
+        }
+        Conversion::Wrap => {
+            body.unop(UnaryOp::I32WrapI64);
+        }
+    }
+}
+
+/// Way an import parameter is passed to the replacement function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamPass {
+    /// Passed as a single parameter, converted if needed.
+    Convert(Conversion),
+    /// The i64 parameter is split into two i32 parameters, the low half first.
+    SplitI64,
+}
+
+/// Adapter body checked against the rules, building it does not fail.
+struct AdapterPlan {
+    params: Vec<ParamPass>,
+    constants: Vec<Value>,
+    results: Vec<Conversion>,
+}
+
+/// Check that the rules allow bridging the import signature to the replacement signature.
+///
+/// returns the plan of the adapter body, or None if the signatures cannot be bridged
+fn plan_adapter(
+    params: &[ValType],
+    results: &[ValType],
+    replacement_params: &[ValType],
+    replacement_results: &[ValType],
+    rules: &[AdapterRule],
+) -> Option<AdapterPlan> {
+    // pass parameters
+    let mut passes = Vec::new();
+    let mut rep_idx = 0;
+
+    for param in params {
+        let Some(&rep_param) = replacement_params.get(rep_idx) else {
+            log::debug!("The replacement has fewer parameters");
+            return None;
+        };
+
+        let split = rules.contains(&AdapterRule::SplitI64)
+            && *param == ValType::I64
+            && replacement_params.get(rep_idx..rep_idx + 2) == Some(&[ValType::I32, ValType::I32]);
+
+        if split {
+            passes.push(ParamPass::SplitI64);
+            rep_idx += 2;
+            continue;
+        }
+
+        let Some(conversion) = conversion(*param, rep_param, rules) else {
+            log::debug!("Cannot convert parameter {param} to {rep_param}");
+            return None;
+        };
+
+        passes.push(ParamPass::Convert(conversion));
+        rep_idx += 1;
+    }
+
+    // supply constants for the remaining parameters
+    let extra_params = &replacement_params[rep_idx..];
+    let mut constants = Vec::new();
+
+    if !extra_params.is_empty() {
+        let values = rules.iter().find_map(|rule| match rule {
+            AdapterRule::ConstParams(values) if values.len() >= extra_params.len() => Some(values),
+            _ => None,
+        });
+
+        let Some(values) = values else {
+            log::debug!("No constants for the extra parameters");
+            return None;
+        };
+
+        for (ty, value) in extra_params.iter().zip(values.iter()) {
+            constants.push(const_value(*ty, *value)?);
+        }
+    }
+
+    // the results expected by the import are pushed back from the stored replacement results
+    if replacement_results.len() < results.len()
+        || (replacement_results.len() > results.len() && !rules.contains(&AdapterRule::DropResults))
+    {
+        log::debug!("Cannot adapt the results");
+        return None;
+    }
+
+    let mut conversions = Vec::new();
+
+    for (rep_result, result) in replacement_results.iter().zip(results.iter()) {
+        let Some(conversion) = conversion(*rep_result, *result, rules) else {
+            log::debug!("Cannot convert result {rep_result} to {result}");
+            return None;
+        };
+
+        conversions.push(conversion);
+    }
+
+    Some(AdapterPlan {
+        params: passes,
+        constants,
+        results: conversions,
+    })
+}
+
+/// Generate a local function with the type of the imported function `fn_id` calling the replacement function
+/// `replacement_id` of a different type, the differences are bridged according to the adapter rules.
+///
+/// returns the ID of the generated adapter function, or None if the rules do not allow to bridge the signatures,
+/// the module is left unchanged in that case
+pub(crate) fn generate_adapter(
+    module: &mut walrus::Module,
+    fn_id: FunctionId,
+    replacement_id: FunctionId,
+    module_name: &str,
+    import_name: &str,
+    rules: &[AdapterRule],
+) -> Option<FunctionId> {
+    let ty = module.types.get(module.funcs.get(fn_id).ty());
+    let params = ty.params().to_vec();
+    let results = ty.results().to_vec();
+
+    let replacement_ty = module.types.get(module.funcs.get(replacement_id).ty());
+    let replacement_params = replacement_ty.params().to_vec();
+    let replacement_results = replacement_ty.results().to_vec();
+
+    let Some(plan) = plan_adapter(
+        &params,
+        &results,
+        &replacement_params,
+        &replacement_results,
+        rules,
+    ) else {
+        log::debug!("No adapter for {module_name}::{import_name}");
+        return None;
+    };
+
+    let args: Vec<LocalId> = params.iter().map(|ty| module.locals.add(*ty)).collect();
+    let result_locals: Vec<LocalId> = replacement_results
+        .iter()
+        .map(|ty| module.locals.add(*ty))
+        .collect();
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    builder.name(format!("__wasi2ic_adapter_{module_name}_{import_name}"));
+
+    let mut body = builder.func_body();
+
+    for (arg, pass) in args.iter().zip(plan.params.iter()) {
+        match pass {
+            ParamPass::SplitI64 => {
+                body.local_get(*arg)
+                    .unop(UnaryOp::I32WrapI64)
+                    .local_get(*arg)
+                    .i64_const(32)
+                    .binop(BinaryOp::I64ShrU)
+                    .unop(UnaryOp::I32WrapI64);
+            }
+            ParamPass::Convert(conversion) => {
+                body.local_get(*arg);
+                convert_value(&mut body, *conversion);
+            }
+        }
+    }
+
+    for value in plan.constants {
+        body.const_(value);
+    }
+
+    body.call(replacement_id);
+
+    // store the results, then push back the ones expected by the import
+    for local in result_locals.iter().rev() {
+        body.local_set(*local);
+    }
+
+    for (local, conversion) in result_locals.iter().zip(plan.results.iter()) {
+        body.local_get(*local);
+        convert_value(&mut body, *conversion);
+    }
+
+    log::debug!("Generated adapter for {module_name}::{import_name}");
+
+    Some(builder.finish(args, &mut module.funcs))
+}
@@ -1,7 +1,108 @@
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Parser, Debug)]
-#[command(version, about=format!("Wasi dependency removal V{}", env!("CARGO_PKG_VERSION")), long_about = None)]
+use clap::{Parser, ValueEnum};
+
+use wasi2ic::{
+    AdapterRule, CheckOptions, CommandMode, ImportMapping, InitMode, LimitsProfile, Metadata,
+    MetadataVisibility, OptimizeLevel, Options, PrefixResolver, StartOrder, StripSection, StubMode,
+    Wasi2icError, CANDID_SERVICE, DEFAULT_PREFIX, DEFAULT_TEMPLATE, INITIALIZE, LIMITS_PROFILES,
+};
+
+/// Format of the information printed to the standard output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text.
+    #[default]
+    Text,
+
+    /// JSON document, see the README for the schema.
+    Json,
+}
+
+/// Metadata embedded from the content of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataFile {
+    /// Metadata name, e.g. `candid:args`.
+    pub name: String,
+
+    /// File read into the section.
+    pub path: PathBuf,
+
+    /// Visibility of the section.
+    pub visibility: MetadataVisibility,
+}
+
+impl FromStr for MetadataFile {
+    type Err = Wasi2icError;
+
+    /// Parse the metadata file given as `NAME=PATH`, public by default, or prefixed with the visibility,
+    /// e.g. `private:NAME=PATH`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (visibility, rest) = match s.split_once(':') {
+            Some((visibility @ ("public" | "private"), rest)) => (visibility.parse()?, rest),
+            _ => (MetadataVisibility::Public, s),
+        };
+
+        let (name, path) = rest.split_once('=').ok_or_else(|| {
+            Wasi2icError::InvalidOption(format!(
+                "Expected [public:|private:]NAME=PATH, found '{s}'"
+            ))
+        })?;
+
+        if name.is_empty() || path.is_empty() {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "Empty metadata name or file path in '{s}'"
+            )));
+        }
+
+        Ok(MetadataFile {
+            name: name.to_string(),
+            path: PathBuf::from(path),
+            visibility,
+        })
+    }
+}
+
+/// returns the content of the file embedded as metadata, the error names the file
+fn read_metadata_file(path: &Path) -> Result<Vec<u8>, Wasi2icError> {
+    std::fs::read(path).map_err(|e| {
+        Wasi2icError::Io(std::io::Error::new(
+            e.kind(),
+            format!("Failed to read the metadata file '{}': {e}", path.display()),
+        ))
+    })
+}
+
+fn parse_import_stub(s: &str) -> Result<(String, StubMode), Wasi2icError> {
+    let (name, mode) = s.split_once('=').ok_or_else(|| {
+        Wasi2icError::InvalidOption(format!("Expected [MODULE::]NAME=MODE, found '{s}'"))
+    })?;
+
+    Ok((name.to_string(), mode.parse()?))
+}
+
+/// Exit codes of the failed conversions, shown after the options in the help.
+const EXIT_CODES: &str = "\
+Exit codes on errors (see --check for the check mode codes):
+  10 - reading or writing a file failed
+  11 - invalid Wasm module, Wasm text or gzip stream
+  12 - invalid option value
+  13 - the import mapping file cannot be read or parsed
+  14 - imports incompatible with the Internet Computer remain
+  15 - ic0 imports not matching the System API
+  16 - the module exceeds the Internet Computer limits
+  17 - some of the batch modules failed to convert
+  18 - the converted module still imports the functions recorded as replaced
+  19 - the optimizer failed
+  20 - the module is a rejected WASI command
+  21 - the module cannot be printed as Wasm text
+  22 - the import mapping cannot be applied to the module";
+
+#[derive(Parser, Debug, Default, Clone)]
+#[command(version, about=format!("Wasi dependency removal V{}", env!("CARGO_PKG_VERSION")), long_about = None,
+    after_help = EXIT_CODES)]
 pub struct Wasm2icArgs {
     /// Quiet mode
     #[arg(long, short, default_value_t = false)]
@@ -11,10 +112,261 @@ pub struct Wasm2icArgs {
     #[arg(long, short, default_value_t = false)]
     pub imports: bool,
 
-    /// Input file to process (*.wasm or *.wat).
+    /// Check if the module converts cleanly without writing the output file.
+    /// Exit codes: 0 - no conversion needed, 3 - convertible, 4 - unresolved imports remain, 5 - invalid input,
+    /// 6 - limits exceeded, 7 - ic0 imports not matching the System API
+    #[arg(long, default_value_t = false, conflicts_with = "imports")]
+    pub check: bool,
+
+    /// Prefix of the replacement functions [default: __ic_custom_]
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Replacement function name template, supports {prefix}, {module} and {name} placeholders [default: {prefix}{name}]
+    #[arg(long)]
+    pub prefix_template: Option<String>,
+
+    /// Import mapping file (*.toml or *.json) rewiring imports to the explicitly named functions
+    #[arg(long)]
+    pub mapping: Option<String>,
+
+    /// Additional import module to rewire besides wasi_unstable and wasi_snapshot_preview1 (e.g. env), can be repeated
+    #[arg(long = "module", value_name = "MODULE")]
+    pub modules: Vec<String>,
+
+    /// Generate stubs for the imports left without replacement: unreachable, trap, errno (returns ENOSYS) or errno:<value>
+    #[arg(long, value_name = "MODE")]
+    pub stubs: Option<StubMode>,
+
+    /// Stub mode for an individual import, e.g. fd_advise=errno:52 or env::my_func=trap, a bare name refers to the WASI modules, can be repeated
+    #[arg(long = "stub", value_name = "[MODULE::]NAME=MODE", value_parser = parse_import_stub)]
+    pub import_stubs: Vec<(String, StubMode)>,
+
+    /// Do not validate WASI imports and their replacements against the WASI specification signatures
+    #[arg(long, default_value_t = false)]
+    pub skip_wasi_signature_check: bool,
+
+    /// Skip checking the ic0 imports against the Internet Computer System API specification
+    #[arg(long, default_value_t = false)]
+    pub skip_ic0_check: bool,
+
+    /// Report the ic0 imports unknown to the tool as errors, by default they are warnings,
+    /// as the System API may have gained functions after this tool version
+    #[arg(long, default_value_t = false, conflicts_with = "skip_ic0_check")]
+    pub strict_ic0: bool,
+
+    /// Limits profile to check the converted module against, the most recent built-in profile is used by default
+    #[arg(long, value_name = "PROFILE")]
+    pub limits_profile: Option<String>,
+
+    /// Skip checking the converted module against the Internet Computer limits
+    #[arg(long, default_value_t = false, conflicts_with = "limits_profile")]
+    pub skip_limits_check: bool,
+
+    /// Generate adapters for the replacements of a different type: integers, split-i64, drop-results or const-params:<values>, can be repeated
+    #[arg(long = "adapt", value_name = "RULE")]
+    pub adapters: Vec<AdapterRule>,
+
+    /// Convert the module again even if it was already converted by wasi2ic, such modules are left unchanged by default
+    #[arg(long, default_value_t = false)]
+    pub reconvert: bool,
+
+    /// Keep the DWARF debug sections and update their code addresses to match the converted module
+    #[arg(long, default_value_t = false)]
+    pub keep_dwarf: bool,
+
+    /// Remove sections from the converted module: names, dwarf, producers, target-features or all
+    /// (every custom section except the icp: metadata), comma separated, e.g. "--strip=names,dwarf", "--strip" alone removes all
+    #[arg(
+        long,
+        value_name = "SECTIONS",
+        value_delimiter = ',',
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all"
+    )]
+    pub strip: Vec<StripSection>,
+
+    /// Candid interface file (*.did) embedded into the public candid:service metadata section
+    #[arg(long, value_name = "FILE")]
+    pub candid: Option<String>,
+
+    /// Metadata embedded into the icp:public or icp:private section, e.g. private:git_commit=abc123, can be repeated
+    #[arg(long, value_name = "[public:|private:]NAME=VALUE")]
+    pub metadata: Vec<Metadata>,
+
+    /// Metadata embedded from the file content, e.g. private:candid:args=args.txt, can be repeated
+    #[arg(long, value_name = "[public:|private:]NAME=PATH")]
+    pub metadata_file: Vec<MetadataFile>,
+
+    /// Shrink (like ic-wasm shrink, keeping the name section) and optimize the converted module: shrink, O0, O1, O2, O3, O4, Os or Oz
+    #[arg(long, value_name = "LEVEL")]
+    pub optimize: Option<OptimizeLevel>,
+
+    /// How _initialize is called: start (the module start function) or hooks (at the beginning of canister_init and
+    /// canister_post_upgrade, generated if missing, so that the System API can be used during the initialization)
+    #[arg(long, value_name = "MODE", default_value_t = InitMode::Start)]
+    pub init_mode: InitMode,
+
+    /// Call order when the module already has a start function: initialize-first or start-first,
+    /// a start function calling both _initialize and the original start function is generated
+    #[arg(long, value_name = "ORDER", default_value_t = StartOrder::InitializeFirst)]
+    pub start_order: StartOrder,
+
+    /// Initializer function name, can be repeated to give the candidates in the priority order, e.g.
+    /// "--initializer _initialize --initializer __wasm_call_ctors" (default: _initialize)
+    #[arg(long = "initializer", value_name = "NAME")]
+    pub initializers: Vec<String>,
+
+    /// Handling of the WASI commands exporting _start: reject (fail the conversion) or init (run _start as the
+    /// initializer, proc_exit gets a trapping stub if there is no replacement)
+    #[arg(long, value_name = "MODE")]
+    pub command: Option<CommandMode>,
+
+    /// Compress the output Wasm with gzip, this is also done for the output files with the .gz extension,
+    /// cannot be used with a .wat output, the batch outputs get the .wasm.gz extension
+    #[arg(long, default_value_t = false)]
+    pub gzip: bool,
+
+    /// Output format: text or json, the JSON document is printed even in quiet mode
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Directory to write the converted modules into, enables the batch conversion
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["imports", "check"])]
+    pub out_dir: Option<String>,
+
+    /// Suffix appended to the converted module file names, e.g. "_ic" writes "a.wasm" into "a_ic.wasm",
+    /// enables the batch conversion
+    #[arg(long, conflicts_with_all = ["imports", "check"])]
+    pub suffix: Option<String>,
+
+    /// Input file to process (*.wasm, *.wasm.gz or *.wat) and the output file to store the processed Wasm
+    /// (*.wasm, *.wasm.gz or *.wat, default: no_wasi.wasm). The input format is detected from the file content,
+    /// "-" reads the standard input or writes Wasm to the standard output.
+    /// In batch mode (--out-dir or --suffix): input files, directories or glob patterns.
+    #[arg(value_name = "FILES", required = true)]
+    pub files: Vec<String>,
+
+    /// Input file to process.
+    #[arg(skip)]
     pub input_file: String,
 
-    /// Output file to store the processed Wasm (*.wasm or *.wat).
-    #[arg(default_value_t = String::from("no_wasi.wasm"))]
+    /// Output file to store the processed Wasm.
+    #[arg(skip)]
     pub output_file: String,
 }
+
+impl Wasm2icArgs {
+    /// returns true if multiple modules are converted into the output directory or with the file name suffix
+    pub fn is_batch(&self) -> bool {
+        self.out_dir.is_some() || self.suffix.is_some()
+    }
+
+    /// Set the input and output file from the files given on the command line.
+    pub fn resolve_files(&mut self) -> Result<(), Wasi2icError> {
+        match self.files.as_slice() {
+            [input_file] => {
+                self.input_file = input_file.clone();
+                self.output_file = "no_wasi.wasm".to_string();
+            }
+            [input_file, output_file] => {
+                self.input_file = input_file.clone();
+                self.output_file = output_file.clone();
+            }
+            _ => {
+                return Err(Wasi2icError::InvalidOption(
+                    "Expected the input and the output file, use --out-dir or --suffix to convert multiple files"
+                        .to_string(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn conversion_options(&self) -> Result<Options, Wasi2icError> {
+        let prefix = self.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+        let template = self.prefix_template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+
+        let mapping = match &self.mapping {
+            Some(path) => ImportMapping::from_file(Path::new(path))?,
+            None => ImportMapping::default(),
+        };
+
+        let mut options = Options {
+            resolver: Arc::new(PrefixResolver::with_template(prefix, template)),
+            mapping,
+            stubs: self.stubs,
+            import_stubs: self.import_stubs.iter().cloned().collect(),
+            validate_wasi_signatures: !self.skip_wasi_signature_check,
+            adapters: self.adapters.clone(),
+            reconvert: self.reconvert,
+            keep_dwarf: self.keep_dwarf,
+            strip: self.strip.clone(),
+            optimize: self.optimize,
+            init_mode: self.init_mode,
+            start_order: self.start_order,
+            initializers: if self.initializers.is_empty() {
+                vec![INITIALIZE.to_string()]
+            } else {
+                self.initializers.clone()
+            },
+            command: self.command,
+            ..Default::default()
+        };
+
+        if let Some(path) = &self.candid {
+            options.metadata.push(Metadata {
+                name: CANDID_SERVICE.to_string(),
+                data: read_metadata_file(Path::new(path))?,
+                visibility: MetadataVisibility::Public,
+            });
+        }
+
+        options.metadata.extend(self.metadata.iter().cloned());
+
+        for file in &self.metadata_file {
+            options.metadata.push(Metadata {
+                name: file.name.clone(),
+                data: read_metadata_file(&file.path)?,
+                visibility: file.visibility,
+            });
+        }
+
+        for module in &self.modules {
+            if !options.modules.contains(module) {
+                options.modules.push(module.clone());
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// returns the options of the module compatibility check
+    pub fn check_options(&self) -> Result<CheckOptions, Wasi2icError> {
+        Ok(CheckOptions {
+            skip_ic0_check: self.skip_ic0_check,
+            strict_ic0: self.strict_ic0,
+            limits_profile: self.limits_profile()?.cloned(),
+        })
+    }
+
+    /// returns the limits profile to check the converted module against, or None if the limits check is skipped
+    pub fn limits_profile(&self) -> Result<Option<&'static LimitsProfile>, Wasi2icError> {
+        if self.skip_limits_check {
+            return Ok(None);
+        }
+
+        match &self.limits_profile {
+            Some(name) => LimitsProfile::find(name).map(Some).ok_or_else(|| {
+                let names: Vec<&str> = LIMITS_PROFILES.iter().map(|p| p.name).collect();
+                Wasi2icError::InvalidOption(format!(
+                    "Unknown limits profile '{name}', expected one of: {}",
+                    names.join(", ")
+                ))
+            }),
+            None => Ok(Some(LimitsProfile::latest())),
+        }
+    }
+}
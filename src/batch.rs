@@ -0,0 +1,287 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use wasi2ic::Wasi2icError;
+
+use crate::arguments::{OutputFormat, Wasm2icArgs};
+use crate::{do_wasm_file_processing_with_output, output, STDIO};
+
+/// Extensions of the module files picked up from the input directories, the longest extensions go first.
+const MODULE_EXTENSIONS: &[&str] = &[".wasm.gz", ".wasm", ".wat"];
+
+/// Conversion outcome of a single module in the batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub input_file: String,
+    pub output_file: String,
+
+    /// Error message, None if the module was converted successfully.
+    pub error: Option<String>,
+
+    /// JSON document of the module conversion, only set with the JSON output format.
+    pub document: Option<Value>,
+}
+
+fn module_extension(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+
+    MODULE_EXTENSIONS
+        .iter()
+        .find(|ext| name.len() > ext.len() && name.ends_with(*ext))
+        .copied()
+}
+
+/// Expand the input files, directories and glob patterns into the list of module files.
+pub fn expand_inputs(files: &[String]) -> Result<Vec<PathBuf>, Wasi2icError> {
+    let mut inputs: Vec<PathBuf> = Vec::new();
+
+    for file in files {
+        if file == STDIO {
+            return Err(Wasi2icError::InvalidOption(
+                "The standard input cannot be used in batch mode".to_string(),
+            ));
+        }
+
+        let path = Path::new(file);
+
+        let mut found = if path.is_dir() {
+            std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|path| path.is_file() && module_extension(path).is_some())
+                .collect()
+        } else if file.contains(['*', '?', '[']) {
+            let found = glob::glob(file)
+                .map_err(|e| Wasi2icError::InvalidOption(format!("Invalid pattern '{file}': {e}")))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Wasi2icError::Io(e.into_error()))?
+                .into_iter()
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>();
+
+            if found.is_empty() {
+                return Err(Wasi2icError::InvalidOption(format!(
+                    "No files match the pattern '{file}'"
+                )));
+            }
+
+            found
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        found.sort();
+
+        for path in found {
+            if !inputs.contains(&path) {
+                inputs.push(path);
+            }
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// returns the output file path: the input file name with the suffix inserted before the extension,
+/// placed into the output directory or next to the input file, the extension is `.wasm.gz` for the compressed output
+pub fn output_path(input: &Path, out_dir: Option<&str>, suffix: &str, gzip: bool) -> PathBuf {
+    let name = input
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let input_extension = module_extension(input).unwrap_or("");
+    let stem = &name[..name.len() - input_extension.len()];
+
+    let extension = if gzip { ".wasm.gz" } else { input_extension };
+
+    let dir = match out_dir {
+        Some(out_dir) => PathBuf::from(out_dir),
+        None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    dir.join(format!("{stem}{suffix}{extension}"))
+}
+
+/// Convert the modules in parallel, then print the messages of each module and the summary into `out`.
+///
+/// With the JSON output format a single document is printed, with the module documents nested in the summary.
+///
+/// returns an error if any of the modules failed to convert
+pub fn do_batch_processing(args: &Wasm2icArgs, out: &mut dyn Write) -> Result<(), Wasi2icError> {
+    let inputs = expand_inputs(&args.files)?;
+
+    if inputs.is_empty() {
+        return Err(Wasi2icError::InvalidOption(
+            "No input modules found".to_string(),
+        ));
+    }
+
+    let suffix = args.suffix.as_deref().unwrap_or("");
+
+    let mut jobs = Vec::new();
+    let mut outputs: Vec<PathBuf> = Vec::new();
+    for input in &inputs {
+        let output = output_path(input, args.out_dir.as_deref(), suffix, args.gzip);
+
+        if inputs.contains(&output) {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "The output file '{}' would overwrite an input file, use a different --out-dir or --suffix",
+                output.display()
+            )));
+        }
+
+        // inputs of the same name from different directories would be written into the same output file
+        if outputs.contains(&output) {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "Multiple input files are converted into the same output file '{}', convert them separately",
+                output.display()
+            )));
+        }
+
+        outputs.push(output.clone());
+
+        let mut job_args = args.clone();
+        job_args.input_file = input.to_string_lossy().to_string();
+        job_args.output_file = output.to_string_lossy().to_string();
+        jobs.push(job_args);
+    }
+
+    if let Some(out_dir) = &args.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+
+    let next_job = AtomicUsize::new(0);
+    let finished: Mutex<Vec<(usize, Vec<u8>, BatchResult)>> = Mutex::new(Vec::new());
+
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(jobs.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some(job_args) = jobs.get(index) else {
+                    break;
+                };
+
+                let mut messages = Vec::new();
+                let result = do_wasm_file_processing_with_output(job_args, &mut messages);
+
+                let document = match job_args.format {
+                    OutputFormat::Text => None,
+                    OutputFormat::Json => serde_json::from_slice(&messages).ok(),
+                };
+
+                let result = BatchResult {
+                    input_file: job_args.input_file.clone(),
+                    output_file: job_args.output_file.clone(),
+                    error: result.err().map(|e| e.to_string()),
+                    document,
+                };
+
+                finished
+                    .lock()
+                    .expect("batch results lock is not poisoned")
+                    .push((index, messages, result));
+            });
+        }
+    });
+
+    let mut finished = finished
+        .into_inner()
+        .expect("batch results lock is not poisoned");
+    finished.sort_by_key(|(index, _, _)| *index);
+
+    // messages are printed in the input order once all modules are done, so that they do not interleave,
+    // the JSON documents are nested into the summary document instead
+    if args.format == OutputFormat::Text {
+        for (_, messages, _) in &finished {
+            out.write_all(messages)?;
+        }
+    }
+
+    let results: Vec<BatchResult> = finished.into_iter().map(|(_, _, result)| result).collect();
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+
+    match args.format {
+        OutputFormat::Text => show_batch_summary(out, &results, args.quiet)?,
+        OutputFormat::Json => writeln!(out, "{:#}", output::batch_document(&results))?,
+    }
+
+    if failed > 0 {
+        return Err(Wasi2icError::BatchFailed {
+            failed,
+            total: results.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Print the summary table, only the failed modules are listed in quiet mode.
+pub fn show_batch_summary(
+    out: &mut dyn Write,
+    results: &[BatchResult],
+    quiet: bool,
+) -> std::io::Result<()> {
+    let results: Vec<&BatchResult> = results
+        .iter()
+        .filter(|r| !quiet || r.error.is_some())
+        .collect();
+
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    let input_width = results
+        .iter()
+        .map(|r| r.input_file.len())
+        .chain(["INPUT".len()])
+        .max()
+        .unwrap_or(0);
+    let output_width = results
+        .iter()
+        .map(|r| r.output_file.len())
+        .chain(["OUTPUT".len()])
+        .max()
+        .unwrap_or(0);
+
+    writeln!(out, "Summary:")?;
+    writeln!(
+        out,
+        "  {:<6}  {:<input_width$}  {:<output_width$}  ERROR",
+        "STATUS", "INPUT", "OUTPUT"
+    )?;
+
+    for result in &results {
+        let status = if result.error.is_some() {
+            "failed"
+        } else {
+            "ok"
+        };
+
+        writeln!(
+            out,
+            "  {:<6}  {:<input_width$}  {:<output_width$}  {}",
+            status,
+            result.input_file,
+            result.output_file,
+            result.error.as_deref().unwrap_or("")
+        )?;
+    }
+
+    if !quiet {
+        let converted = results.iter().filter(|r| r.error.is_none()).count();
+        writeln!(out, "Converted {converted} of {} modules", results.len())?;
+    }
+
+    Ok(())
+}
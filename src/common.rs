@@ -2,21 +2,38 @@ use std::collections::{HashMap, HashSet};
 use walrus::ElementItems;
 use walrus::{ir::Instr, FunctionId};
 
-const WASI_UNSTABLE: &str = "wasi_unstable";
-const WASI_SNAPSHOT_PREVIEW1: &str = "wasi_snapshot_preview1";
+use crate::adapters::generate_adapter;
+use crate::error::Wasi2icError;
+use crate::info::{read_info, write_info, ConversionInfo};
+use crate::init::{
+    add_init_hooks, chain_start_function, command_options, find_initializer, InitMode,
+};
+use crate::metadata::add_metadata;
+use crate::optimize::{optimize_module, OptimizeLevel};
+use crate::options::Options;
+use crate::report::{ConversionReport, ImportReport, ImportResolution, Signature, StubReport};
+use crate::strip::{
+    custom_sections, encoded_section_size, strip_sections, strips, CustomSection, StripSection,
+};
+use crate::stubs::generate_stub;
+use crate::wasi_signatures::wasi_signature;
+
+pub(crate) const WASI_UNSTABLE: &str = "wasi_unstable";
+pub(crate) const WASI_SNAPSHOT_PREVIEW1: &str = "wasi_snapshot_preview1";
 
 fn get_replacement_module_id(
     module: &walrus::Module,
     module_name: &str,
     import_name: &str,
     fn_id: FunctionId,
-) -> Option<FunctionId> {
-    // we only support wasi_unstable and wasi_snapshot_preview1 modules
-    if module_name != WASI_UNSTABLE && module_name != WASI_SNAPSHOT_PREVIEW1 {
-        return None;
+    options: &Options,
+) -> (ImportResolution, Option<FunctionId>) {
+    // only the modules from the allowlist are rewired
+    if !options.modules.iter().any(|m| m == module_name) {
+        return (ImportResolution::Skipped, None);
     }
 
-    let searched_function_name = format!("__ic_custom_{import_name}");
+    let searched_function_name = options.resolver.replacement_name(module_name, import_name);
 
     // 1) Search by function name
     for fun in module.funcs.iter() {
@@ -26,14 +43,21 @@ fn get_replacement_module_id(
                 let replacement_ty = module.funcs.get(fun.id()).ty();
 
                 if original_ty != replacement_ty {
-                    log::error!(
-                        "Type mismatch for replacement {}::{}: original {:?}, replacement {:?}",
-                        module_name,
-                        import_name,
-                        original_ty,
-                        replacement_ty
+                    let expected = Signature::from_type(module, original_ty);
+                    let actual = Signature::from_type(module, replacement_ty);
+
+                    // an adapter can still bridge the mismatch, the error is logged if it remains unresolved
+                    log::debug!(
+                        "Type mismatch for replacement {module_name}::{import_name}: original {expected}, replacement {actual}"
+                    );
+                    return (
+                        ImportResolution::TypeMismatch {
+                            replacement: searched_function_name,
+                            expected,
+                            actual,
+                        },
+                        None,
                     );
-                    return None;
                 }
 
                 if matches!(module.funcs.get(fun.id()).kind, walrus::FunctionKind::Import(_)) {
@@ -41,7 +65,12 @@ fn get_replacement_module_id(
                         "Replacement function {} must not be an imported function",
                         searched_function_name
                     );
-                    return None;
+                    return (
+                        ImportResolution::ReplacementIsImport {
+                            replacement: searched_function_name,
+                        },
+                        None,
+                    );
                 }
 
                 log::debug!(
@@ -50,7 +79,12 @@ fn get_replacement_module_id(
                     module.funcs.get(fun.id()).name
                 );
 
-                return Some(fun.id());
+                return (
+                    ImportResolution::ReplacedByName {
+                        replacement: searched_function_name,
+                    },
+                    Some(fun.id()),
+                );
             }
         }
     }
@@ -67,14 +101,21 @@ fn get_replacement_module_id(
                 let replacement_ty = module.funcs.get(exported_function).ty();
 
                 if original_ty != replacement_ty {
-                    log::error!(
-                        "Type mismatch for exported replacement {}::{}: original {:?}, replacement {:?}",
-                        module_name,
-                        import_name,
-                        original_ty,
-                        replacement_ty
+                    let expected = Signature::from_type(module, original_ty);
+                    let actual = Signature::from_type(module, replacement_ty);
+
+                    // an adapter can still bridge the mismatch, the error is logged if it remains unresolved
+                    log::debug!(
+                        "Type mismatch for exported replacement {module_name}::{import_name}: original {expected}, replacement {actual}"
+                    );
+                    return (
+                        ImportResolution::TypeMismatch {
+                            replacement: searched_function_name,
+                            expected,
+                            actual,
+                        },
+                        None,
                     );
-                    return None;
                 }
 
                 if matches!(
@@ -85,7 +126,12 @@ fn get_replacement_module_id(
                         "Exported replacement function {} must not be an imported function",
                         searched_function_name
                     );
-                    return None;
+                    return (
+                        ImportResolution::ReplacementIsImport {
+                            replacement: searched_function_name,
+                        },
+                        None,
+                    );
                 }
 
                 log::debug!(
@@ -94,7 +140,12 @@ fn get_replacement_module_id(
                     module.funcs.get(exported_function).name
                 );
 
-                return Some(exported_function);
+                return (
+                    ImportResolution::ReplacedByExport {
+                        replacement: searched_function_name,
+                    },
+                    Some(exported_function),
+                );
             }
             walrus::ExportItem::Table(_)
             | walrus::ExportItem::Memory(_)
@@ -102,26 +153,187 @@ fn get_replacement_module_id(
         }
     }
 
-    log::warn!(
-        "Could not find the replacement for the WASI function: {module_name}::{import_name}"
-    );
+    log::warn!("Could not find the replacement for the function: {module_name}::{import_name}");
+
+    (
+        ImportResolution::Missing {
+            replacement: searched_function_name,
+        },
+        None,
+    )
+}
+
+/// Find the function by its name first, then by its export name.
+fn find_function(module: &walrus::Module, name: &str) -> Option<FunctionId> {
+    module.funcs.by_name(name).or_else(|| {
+        module.exports.iter().find_map(|export| match export.item {
+            walrus::ExportItem::Function(id) if export.name == name => Some(id),
+            _ => None,
+        })
+    })
+}
+
+fn get_mapped_replacement_id(
+    module: &walrus::Module,
+    module_name: &str,
+    import_name: &str,
+    fn_id: FunctionId,
+    target: &str,
+) -> Result<FunctionId, Wasi2icError> {
+    let mapping_error = |reason: String| Wasi2icError::Mapping {
+        module: module_name.to_string(),
+        name: import_name.to_string(),
+        target: target.to_string(),
+        reason,
+    };
+
+    let Some(target_id) = find_function(module, target) else {
+        return Err(mapping_error("does not exist".to_string()));
+    };
+
+    if matches!(
+        module.funcs.get(target_id).kind,
+        walrus::FunctionKind::Import(_)
+    ) {
+        return Err(mapping_error(
+            "must not be an imported function".to_string(),
+        ));
+    }
+
+    let original_ty = module.funcs.get(fn_id).ty();
+    let replacement_ty = module.funcs.get(target_id).ty();
+
+    if original_ty != replacement_ty {
+        return Err(mapping_error(format!(
+            "has a mismatching type: expected {}, found {}",
+            Signature::from_type(module, original_ty),
+            Signature::from_type(module, replacement_ty)
+        )));
+    }
+
+    log::debug!("Mapped function replacement found: {module_name}::{import_name} -> {target}.");
+
+    Ok(target_id)
+}
+
+/// Check the import and its replacement against the signature given by the WASI specification.
+///
+/// returns the resolution describing the mismatch, or None if both signatures are as expected
+fn check_wasi_signature(
+    module: &walrus::Module,
+    module_name: &str,
+    import_name: &str,
+    fn_id: FunctionId,
+    replacement_id: FunctionId,
+    replacement_name: &str,
+) -> Option<ImportResolution> {
+    let expected = wasi_signature(module_name, import_name)?;
+
+    let checks = [
+        (format!("{module_name}::{import_name}"), fn_id),
+        (replacement_name.to_string(), replacement_id),
+    ];
+
+    for (function, id) in checks {
+        let actual = Signature::from_type(module, module.funcs.get(id).ty());
+
+        if actual != expected {
+            log::error!(
+                "Signature mismatch for {function}: expected {expected} by the WASI specification, found {actual}"
+            );
+
+            return Some(ImportResolution::WasiSignatureMismatch {
+                function,
+                expected,
+                actual,
+            });
+        }
+    }
 
     None
 }
 
-pub(crate) fn gather_replacement_ids(m: &walrus::Module) -> HashMap<FunctionId, FunctionId> {
+/// Function replacements found for the module imports.
+pub(crate) struct Replacements {
+    /// Maps the imported function IDs to their replacement function IDs.
+    pub ids: HashMap<FunctionId, FunctionId>,
+
+    /// Resolution outcome for each of the function imports.
+    pub imports: Vec<ImportReport>,
+
+    /// Imported function IDs, in the order of `imports`.
+    pub import_ids: Vec<FunctionId>,
+}
+
+pub(crate) fn gather_replacement_ids(
+    m: &walrus::Module,
+    options: &Options,
+) -> Result<Replacements, Wasi2icError> {
     // gather functions for replacements
     let mut fn_replacement_ids: HashMap<FunctionId, FunctionId> = HashMap::new();
+    let mut imports: Vec<ImportReport> = Vec::new();
+    let mut import_ids: Vec<FunctionId> = Vec::new();
 
     for imp in m.imports.iter() {
         match imp.kind {
             walrus::ImportKind::Function(fn_id) => {
-                let replace_id =
-                    get_replacement_module_id(m, imp.module.as_str(), imp.name.as_str(), fn_id);
+                // explicit mapping goes before the name search
+                let mapped = options.mapping.get(imp.module.as_str(), imp.name.as_str());
+
+                let (resolution, replace_id) = if let Some(target) = mapped {
+                    let rep_id = get_mapped_replacement_id(
+                        m,
+                        imp.module.as_str(),
+                        imp.name.as_str(),
+                        fn_id,
+                        target,
+                    )?;
+
+                    (
+                        ImportResolution::ReplacedByMapping {
+                            replacement: target.to_string(),
+                        },
+                        Some(rep_id),
+                    )
+                } else {
+                    get_replacement_module_id(
+                        m,
+                        imp.module.as_str(),
+                        imp.name.as_str(),
+                        fn_id,
+                        options,
+                    )
+                };
+
+                let (resolution, replace_id) = match replace_id {
+                    Some(rep_id) if options.validate_wasi_signatures => {
+                        let replacement_name = resolution.replacement().unwrap_or_default();
+
+                        match check_wasi_signature(
+                            m,
+                            imp.module.as_str(),
+                            imp.name.as_str(),
+                            fn_id,
+                            rep_id,
+                            replacement_name,
+                        ) {
+                            Some(mismatch) => (mismatch, None),
+                            None => (resolution, Some(rep_id)),
+                        }
+                    }
+                    _ => (resolution, replace_id),
+                };
 
                 if let Some(rep_id) = replace_id {
                     fn_replacement_ids.insert(fn_id, rep_id);
                 }
+
+                imports.push(ImportReport {
+                    module: imp.module.clone(),
+                    name: imp.name.clone(),
+                    resolution,
+                });
+                import_ids.push(fn_id);
             }
 
             walrus::ImportKind::Table(_)
@@ -130,9 +342,21 @@ pub(crate) fn gather_replacement_ids(m: &walrus::Module) -> HashMap<FunctionId,
         }
     }
 
+    for (module_name, import_name, target) in options.mapping.iter() {
+        if m.imports.find(module_name, import_name).is_none() {
+            log::warn!(
+                "Mapping entry {module_name}::{import_name} -> {target} does not match any import"
+            );
+        }
+    }
+
     log::debug!("Gathered replacement IDs {fn_replacement_ids:?}");
 
-    fn_replacement_ids
+    Ok(Replacements {
+        ids: fn_replacement_ids,
+        imports,
+        import_ids,
+    })
 }
 
 fn replace_calls(m: &mut walrus::Module, fn_replacement_ids: &HashMap<FunctionId, FunctionId>) {
@@ -292,66 +516,324 @@ fn replace_calls_in_instructions(
     }
 }
 
-pub(crate) fn add_start_entry(module: &mut walrus::Module) {
-    // try to find the start (_initialize) function
-    let initialize_function = module.funcs.by_name("_initialize");
-    log::info!("_initialize function found: {initialize_function:?}");
-
-    if let Some(initialize) = initialize_function {
-        if module.start.is_none() {
-            log::info!("Setting module start function to _initialize");
-            module.start = Some(initialize);
-        } else {
-            log::debug!("Module already has a start function; leaving it unchanged");
-        }
+pub(crate) fn add_start_entry(
+    module: &mut walrus::Module,
+    initializer: walrus::FunctionId,
+) -> bool {
+    if module.start.is_none() {
+        log::info!("Setting module start function to the initializer {initializer:?}");
+        module.start = Some(initializer);
+        true
+    } else {
+        log::debug!("Module already has a start function; chaining it with the initializer");
+        false
     }
 }
 
-pub(crate) fn remove_start_export(module: &mut walrus::Module) {
-    let mut export_found: Option<walrus::ExportId> = None;
+pub(crate) fn remove_start_export(
+    module: &mut walrus::Module,
+    initializer_name: &str,
+) -> Option<String> {
+    let mut export_found: Option<(walrus::ExportId, String)> = None;
 
     // try to find the start export
     for export in module.exports.iter() {
-        if !export.name.starts_with("_initialize") {
+        if export.name != initializer_name {
             continue;
         }
 
         if let walrus::ExportItem::Function(_) = export.item {
-            export_found = Some(export.id());
+            export_found = Some((export.id(), export.name.clone()));
             break;
         }
     }
 
     // remove export, if it was found
-    if let Some(export_id) = export_found {
-        log::debug!("Removing _initialize export");
+    if let Some((export_id, name)) = export_found {
+        log::debug!("Removing {name} export");
         module.exports.delete(export_id);
+        return Some(name);
     }
+
+    None
 }
 
-pub(crate) fn do_module_replacements(module: &mut walrus::Module) -> bool {
+pub(crate) fn do_module_replacements(
+    module: &mut walrus::Module,
+    options: &Options,
+) -> Result<ConversionReport, Wasi2icError> {
+    if options.keep_dwarf && strips(&options.strip, StripSection::Dwarf) {
+        return Err(Wasi2icError::InvalidOption(
+            "The DWARF sections cannot be both kept and stripped".to_string(),
+        ));
+    }
+
+    if options.keep_dwarf
+        && options
+            .optimize
+            .is_some_and(|level| level != OptimizeLevel::Shrink)
+    {
+        return Err(Wasi2icError::InvalidOption(
+            "The DWARF sections cannot be kept when optimizing the module with wasm-opt"
+                .to_string(),
+        ));
+    }
+
+    let previous_conversion = read_info(module)?;
+
+    // do not rewire a module converted before, only verify it is consistent with the conversion record,
+    // the requested stripping, metadata and optimization are still applied
+    if let Some(info) = &previous_conversion {
+        if !options.reconvert {
+            let imports = info.still_imported(module);
+
+            if !imports.is_empty() {
+                return Err(Wasi2icError::InconsistentConversion {
+                    version: info.version.clone(),
+                    imports,
+                });
+            }
+
+            log::debug!(
+                "Module already converted by wasi2ic {}; imports are not rewired again",
+                info.version
+            );
+
+            let mut report = ConversionReport {
+                previous_conversion,
+                ..Default::default()
+            };
+
+            post_process_module(module, options, &mut report)?;
+
+            return Ok(report);
+        }
+    }
+
+    // WASI commands are rejected or converted to run `_start` as the initializer, depending on the options
+    let command_options = command_options(module, options)?;
+    let options = command_options.as_ref().unwrap_or(options);
+
     // find corresponding IDs for replacements
-    let fn_replacement_ids = gather_replacement_ids(module);
+    let mut replacements = gather_replacement_ids(module, options)?;
+
+    let mut report = ConversionReport {
+        imports: replacements.imports,
+        previous_conversion,
+        ..Default::default()
+    };
+
+    // generate adapters for the replacements with the type different from the import
+    if !options.adapters.is_empty() {
+        for (import, &fn_id) in report.imports.iter_mut().zip(&replacements.import_ids) {
+            let ImportResolution::TypeMismatch { replacement, .. } = &import.resolution else {
+                continue;
+            };
+
+            // a malformed import should not be adapted to its replacement
+            if options.validate_wasi_signatures {
+                let actual = Signature::from_type(module, module.funcs.get(fn_id).ty());
+
+                if wasi_signature(&import.module, &import.name).is_some_and(|s| s != actual) {
+                    continue;
+                }
+            }
+
+            let Some(replacement_id) = find_function(module, replacement) else {
+                continue;
+            };
+
+            let adapter_id = generate_adapter(
+                module,
+                fn_id,
+                replacement_id,
+                &import.module,
+                &import.name,
+                &options.adapters,
+            );
+
+            if let Some(adapter_id) = adapter_id {
+                replacements.ids.insert(fn_id, adapter_id);
+
+                import.resolution = ImportResolution::ReplacedByAdapter {
+                    replacement: replacement.clone(),
+                };
+            }
+        }
+    }
+
+    for import in &report.imports {
+        if let ImportResolution::TypeMismatch {
+            replacement,
+            expected,
+            actual,
+        } = &import.resolution
+        {
+            log::error!(
+                "Type mismatch for replacement {replacement} of {}::{}: original {expected}, replacement {actual}",
+                import.module,
+                import.name
+            );
+        }
+    }
+
+    // generate stubs for the imports left without replacement
+    for (import, &fn_id) in report.imports.iter_mut().zip(&replacements.import_ids) {
+        if import.resolution.is_replaced() || import.resolution == ImportResolution::Skipped {
+            continue;
+        }
+
+        let Some(mode) = options.stub_mode(&import.module, &import.name) else {
+            continue;
+        };
+
+        let (stub_id, mode) = generate_stub(module, fn_id, &import.module, &import.name, &mode);
+
+        replacements.ids.insert(fn_id, stub_id);
+
+        import.resolution = ImportResolution::Stubbed { mode };
 
-    if fn_replacement_ids.is_empty() {
+        report.stubs.push(StubReport {
+            module: import.module.clone(),
+            name: import.name.clone(),
+            mode,
+        });
+    }
+
+    if replacements.ids.is_empty() {
         // do not modify module, if there are no functions to rewire
         log::debug!("No WASI imports to replace; leaving module unchanged");
-        return false;
+    } else {
+        // do recursive call replacement
+        replace_calls(module, &replacements.ids);
+
+        // add the initializer entry (this is needed to do initialization), either as the start function,
+        // or called from the lifecycle exports, where the System API is available
+        if let Some((name, initializer)) = find_initializer(module, &options.initializers)? {
+            log::info!("Initializer function found: {name}");
+
+            match options.init_mode {
+                InitMode::Start => {
+                    report.start_function_set = add_start_entry(module, initializer);
+
+                    if !report.start_function_set {
+                        report.chained_start =
+                            chain_start_function(module, initializer, options.start_order);
+                        report.start_function_set = report.chained_start.is_some();
+                    }
+                }
+                InitMode::Hooks => report.init_hooks = add_init_hooks(module, initializer),
+            }
+
+            // remove the initializer export to clean up the module exports
+            report
+                .removed_exports
+                .extend(remove_start_export(module, &name));
+
+            report.initializer = Some(name);
+        } else {
+            log::debug!(
+                "No initializer function found among: {}",
+                options.initializers.join(", ")
+            );
+        }
+
+        // clean-up unused imports
+        walrus::passes::gc::run(module);
+
+        report.modified = true;
+    }
+
+    post_process_module(module, options, &mut report)?;
+
+    Ok(report)
+}
+
+/// Strip the sections, embed the metadata and optimize the module as requested by the options,
+/// then record the conversion in the module.
+fn post_process_module(
+    module: &mut walrus::Module,
+    options: &Options,
+    report: &mut ConversionReport,
+) -> Result<(), Wasi2icError> {
+    let rewired = report.modified;
+
+    // the module is emitted once to measure its custom sections, the sizes after stripping and embedding
+    // the metadata are derived from the sections removed and added
+    let (mut size, sections): (u64, Vec<CustomSection>) =
+        if !options.strip.is_empty() || options.optimize.is_some() {
+            let wasm = emit_wasm_keeping_customs(module);
+            (wasm.len() as u64, custom_sections(&wasm))
+        } else {
+            (0, Vec::new())
+        };
+
+    // stripping is done even if there are no functions to rewire, the replacement functions are found by name,
+    // so the names can only be removed at the end
+    if !options.strip.is_empty() {
+        let stripped = strip_sections(module, &options.strip, &sections);
+
+        size -= stripped.bytes_saved;
+
+        report.modified |= !stripped.sections.is_empty();
+        report.stripped = Some(stripped);
     }
 
-    // do recursive call replacement
-    replace_calls(module, &fn_replacement_ids);
+    // the metadata is embedded after stripping, so that it is never removed
+    if !options.metadata.is_empty() {
+        report.metadata = add_metadata(module, &options.metadata);
+        report.modified = true;
+
+        // the sections of the same metadata are replaced, in either visibility
+        let replaced = sections.iter().filter(|section| {
+            options.metadata.iter().any(|metadata| {
+                section.name == format!("icp:public {}", metadata.name)
+                    || section.name == format!("icp:private {}", metadata.name)
+            })
+        });
+        size -= replaced.map(|section| section.encoded_size()).sum::<u64>();
+        size += report
+            .metadata
+            .iter()
+            .map(|(name, data_len)| encoded_section_size(name, *data_len))
+            .sum::<u64>();
+    }
+
+    if let Some(level) = options.optimize {
+        report.optimized = Some(optimize_module(module, level, size)?);
+        report.modified = true;
+    }
 
-    // add _initialize entry (this is needed to do initialization)
-    add_start_entry(module);
+    // record the conversion last, so that the record is not removed by shrinking the module,
+    // the module is then not converted again on later runs
+    if rewired {
+        write_info(module, &ConversionInfo::from_report(report));
+    } else if let (true, Some(info)) = (report.modified, &report.previous_conversion) {
+        write_info(module, info);
+    }
 
-    // remove the _initialize export to clean up the module exports
-    remove_start_export(module);
+    Ok(())
+}
 
-    // clean-up unused imports
-    walrus::passes::gc::run(module);
+/// Emit the module binary keeping the custom sections in the module,
+/// `walrus::Module::emit_wasm` moves them out, so that they are missing from any later emission.
+pub(crate) fn emit_wasm_keeping_customs(module: &mut walrus::Module) -> Vec<u8> {
+    let customs: Vec<walrus::RawCustomSection> = module
+        .customs
+        .iter()
+        .map(|(_, section)| walrus::RawCustomSection {
+            name: section.name().to_string(),
+            data: section.data(&walrus::IdsToIndices::default()).into_owned(),
+        })
+        .collect();
+
+    let wasm = module.emit_wasm();
+
+    for section in customs {
+        module.customs.add(section);
+    }
 
-    true
+    wasm
 }
 
 pub(crate) fn get_module_imports(module: &walrus::Module) -> Vec<(String, String)> {
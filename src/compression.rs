@@ -0,0 +1,36 @@
+use std::io::{Read, Write};
+
+use flate2::{read::GzDecoder, Compression, GzBuilder};
+
+use crate::error::Wasi2icError;
+
+/// Magic bytes starting a gzip stream.
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// returns true if the bytes start with the gzip magic bytes
+pub(crate) fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompress the gzip stream.
+pub(crate) fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, Wasi2icError> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Wasi2icError::Parse(format!("Invalid gzip stream: {e}")))?;
+
+    Ok(decompressed)
+}
+
+/// Compress the bytes with gzip.
+///
+/// The output is deterministic: the best compression level is used and the header carries no file name or timestamp,
+/// so the same module always produces the same bytes.
+pub(crate) fn gzip(bytes: &[u8]) -> Result<Vec<u8>, Wasi2icError> {
+    let mut encoder = GzBuilder::new()
+        .mtime(0)
+        .write(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+
+    Ok(encoder.finish()?)
+}
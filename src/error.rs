@@ -0,0 +1,88 @@
+use thiserror::Error;
+
+use crate::limits::LimitViolation;
+use crate::report::Ic0ImportIssue;
+
+fn join<T: ToString>(items: impl IntoIterator<Item = T>) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Errors of the module conversion.
+#[derive(Debug, Error)]
+pub enum Wasi2icError {
+    /// Reading or writing a file or stream failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The input is not a valid binary Wasm module, Wasm text or gzip stream.
+    #[error("Failed to parse the module: {0}")]
+    Parse(String),
+
+    /// An option value is invalid, e.g. an unknown stub mode or limits profile.
+    #[error("{0}")]
+    InvalidOption(String),
+
+    /// The import mapping file cannot be read or parsed.
+    #[error("{0}")]
+    MappingFile(String),
+
+    /// The mapping entry for the import `module::name` cannot be applied to the module.
+    #[error("Mapping target '{target}' for the import {module}::{name} {reason}")]
+    Mapping {
+        module: String,
+        name: String,
+        target: String,
+        reason: String,
+    },
+
+    /// Imports other than ic0 remain after the conversion, given as (module name, function name) pairs.
+    #[error(
+        "There are imports remaining that are not compatible with the Internet Computer: {}",
+        join(imports.iter().map(|(module, name)| format!("{module}::{name}")))
+    )]
+    UnresolvedImports { imports: Vec<(String, String)> },
+
+    /// The ic0 imports do not match the Internet Computer System API.
+    #[error(
+        "There are ic0 imports not matching the Internet Computer System API: {}",
+        join(issues)
+    )]
+    InvalidIc0Imports { issues: Vec<Ic0ImportIssue> },
+
+    /// The module was already converted, but still imports some of the functions recorded as replaced.
+    #[error(
+        "The module was converted by wasi2ic {version}, but still imports the replaced functions: {}",
+        join(imports.iter().map(|(module, name)| format!("{module}::{name}")))
+    )]
+    InconsistentConversion {
+        version: String,
+        imports: Vec<(String, String)>,
+    },
+
+    /// The module is a WASI command, which are rejected by the options.
+    #[error("The module is a WASI command exporting '{entry}', commands are rejected")]
+    CommandModule { entry: String },
+
+    /// The optimizer failed to process the converted module.
+    #[error("Failed to optimize the module: {0}")]
+    Optimize(String),
+
+    /// The converted module cannot be printed in the Wasm text format.
+    #[error("Failed to print the module as Wasm text: {0}")]
+    Print(String),
+
+    /// The module exceeds the Internet Computer limits.
+    #[error(
+        "The module exceeds the Internet Computer limits: {}",
+        join(violations)
+    )]
+    LimitsExceeded { violations: Vec<LimitViolation> },
+
+    /// Some of the modules converted in one invocation failed.
+    #[error("{failed} of {total} modules failed to convert.")]
+    BatchFailed { failed: usize, total: usize },
+}
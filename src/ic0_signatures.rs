@@ -0,0 +1,171 @@
+use crate::report::{Ic0ImportIssue, Signature};
+
+/// Value types of the System API functions.
+#[derive(Clone, Copy)]
+enum ApiType {
+    I32,
+    I64,
+    /// Memory address or size: i32 for 32-bit memory and i64 for 64-bit memory canisters.
+    Address,
+}
+
+use ApiType::{Address as A, I32, I64};
+
+type Ic0Function = (&'static str, &'static [ApiType], &'static [ApiType]);
+
+/// Signatures of the functions in the `ic0` module given by the Internet Computer System API specification,
+/// as of 2025 (including the `env_var_*` functions). Functions added later are reported as unknown.
+const IC0_FUNCTIONS: &[Ic0Function] = &[
+    ("msg_arg_data_size", &[], &[A]),
+    ("msg_arg_data_copy", &[A, A, A], &[]),
+    ("msg_caller_size", &[], &[A]),
+    ("msg_caller_copy", &[A, A, A], &[]),
+    ("msg_reject_code", &[], &[I32]),
+    ("msg_reject_msg_size", &[], &[A]),
+    ("msg_reject_msg_copy", &[A, A, A], &[]),
+    ("msg_deadline", &[], &[I64]),
+    ("msg_reply_data_append", &[A, A], &[]),
+    ("msg_reply", &[], &[]),
+    ("msg_reject", &[A, A], &[]),
+    ("msg_cycles_available", &[], &[I64]),
+    ("msg_cycles_available128", &[A], &[]),
+    ("msg_cycles_refunded", &[], &[I64]),
+    ("msg_cycles_refunded128", &[A], &[]),
+    ("msg_cycles_accept", &[I64], &[I64]),
+    ("msg_cycles_accept128", &[I64, I64, A], &[]),
+    ("msg_method_name_size", &[], &[A]),
+    ("msg_method_name_copy", &[A, A, A], &[]),
+    ("accept_message", &[], &[]),
+    ("cycles_burn128", &[I64, I64, A], &[]),
+    ("canister_self_size", &[], &[A]),
+    ("canister_self_copy", &[A, A, A], &[]),
+    ("canister_cycle_balance", &[], &[I64]),
+    ("canister_cycle_balance128", &[A], &[]),
+    ("canister_liquid_cycle_balance128", &[A], &[]),
+    ("canister_status", &[], &[I32]),
+    ("canister_version", &[], &[I64]),
+    ("subnet_self_size", &[], &[A]),
+    ("subnet_self_copy", &[A, A, A], &[]),
+    ("call_new", &[A, A, A, A, A, A, A, A], &[]),
+    ("call_on_cleanup", &[A, A], &[]),
+    ("call_data_append", &[A, A], &[]),
+    ("call_with_best_effort_response", &[I32], &[]),
+    ("call_cycles_add", &[I64], &[]),
+    ("call_cycles_add128", &[I64, I64], &[]),
+    ("call_perform", &[], &[I32]),
+    ("stable_size", &[], &[I32]),
+    ("stable_grow", &[I32], &[I32]),
+    ("stable_write", &[I32, I32, I32], &[]),
+    ("stable_read", &[I32, I32, I32], &[]),
+    ("stable64_size", &[], &[I64]),
+    ("stable64_grow", &[I64], &[I64]),
+    ("stable64_write", &[I64, I64, I64], &[]),
+    ("stable64_read", &[I64, I64, I64], &[]),
+    ("root_key_size", &[], &[A]),
+    ("root_key_copy", &[A, A, A], &[]),
+    ("certified_data_set", &[A, A], &[]),
+    ("data_certificate_present", &[], &[I32]),
+    ("data_certificate_size", &[], &[A]),
+    ("data_certificate_copy", &[A, A, A], &[]),
+    ("time", &[], &[I64]),
+    ("global_timer_set", &[I64], &[I64]),
+    ("performance_counter", &[I32], &[I64]),
+    ("is_controller", &[A, A], &[I32]),
+    ("in_replicated_execution", &[], &[I32]),
+    ("cost_call", &[I64, I64, A], &[]),
+    ("cost_create_canister", &[A], &[]),
+    ("cost_http_request", &[I64, I64, A], &[]),
+    ("cost_sign_with_ecdsa", &[A, A, I32, A], &[I32]),
+    ("cost_sign_with_schnorr", &[A, A, I32, A], &[I32]),
+    ("cost_vetkd_derive_key", &[A, A, I32, A], &[I32]),
+    ("env_var_count", &[], &[A]),
+    ("env_var_name_size", &[A], &[A]),
+    ("env_var_name_copy", &[A, A, A, A], &[]),
+    ("env_var_name_exists", &[A, A], &[I32]),
+    ("env_var_value_size", &[A, A], &[A]),
+    ("env_var_value_copy", &[A, A, A, A, A], &[]),
+    ("debug_print", &[A, A], &[]),
+    ("trap", &[A, A], &[]),
+    ("mint_cycles", &[I64], &[I64]),
+    ("mint_cycles128", &[I64, I64, A], &[]),
+];
+
+/// Deprecated 32-bit stable memory functions together with their 64-bit replacements.
+const IC0_DEPRECATED_FUNCTIONS: &[(&str, &str)] = &[
+    ("stable_size", "stable64_size"),
+    ("stable_grow", "stable64_grow"),
+    ("stable_write", "stable64_write"),
+    ("stable_read", "stable64_read"),
+];
+
+/// returns the signature of the System API function, or None if the function is not known
+pub(crate) fn ic0_signature(import_name: &str, memory64: bool) -> Option<Signature> {
+    let to_string = |t: &ApiType| match t {
+        ApiType::I32 => "i32".to_string(),
+        ApiType::I64 => "i64".to_string(),
+        ApiType::Address if memory64 => "i64".to_string(),
+        ApiType::Address => "i32".to_string(),
+    };
+
+    IC0_FUNCTIONS
+        .iter()
+        .find(|(name, _, _)| *name == import_name)
+        .map(|(_, params, results)| Signature {
+            params: params.iter().map(to_string).collect(),
+            results: results.iter().map(to_string).collect(),
+        })
+}
+
+/// Check the `ic0` imports of the module against the System API specification.
+///
+/// The unknown functions are only errors in the strict mode.
+///
+/// returns the unknown functions, type mismatches and deprecated functions found
+pub(crate) fn check_ic0_imports(module: &walrus::Module, strict: bool) -> Vec<Ic0ImportIssue> {
+    let memory64 = module.memories.iter().any(|memory| memory.memory64);
+
+    let mut issues = Vec::new();
+
+    for import in module.imports.iter() {
+        let walrus::ImportKind::Function(fn_id) = import.kind else {
+            continue;
+        };
+
+        if import.module != "ic0" {
+            continue;
+        }
+
+        let import_name = import.name.clone();
+
+        let Some(expected) = ic0_signature(&import_name, memory64) else {
+            issues.push(Ic0ImportIssue::Unknown {
+                name: import_name,
+                strict,
+            });
+            continue;
+        };
+
+        let actual = Signature::from_type(module, module.funcs.get(fn_id).ty());
+
+        if actual != expected {
+            issues.push(Ic0ImportIssue::TypeMismatch {
+                name: import_name,
+                expected,
+                actual,
+            });
+            continue;
+        }
+
+        if let Some((_, replacement)) = IC0_DEPRECATED_FUNCTIONS
+            .iter()
+            .find(|(name, _)| *name == import_name)
+        {
+            issues.push(Ic0ImportIssue::Deprecated {
+                name: import_name,
+                replacement: replacement.to_string(),
+            });
+        }
+    }
+
+    issues
+}
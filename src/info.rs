@@ -0,0 +1,119 @@
+use serde_json::{json, Value};
+
+use crate::error::Wasi2icError;
+use crate::report::ConversionReport;
+
+/// Name of the custom section recording the conversion done by wasi2ic.
+pub const INFO_SECTION_NAME: &str = "wasi2ic:info";
+
+/// Conversion record stored in the `wasi2ic:info` custom section of the converted module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionInfo {
+    /// Version of wasi2ic that converted the module.
+    pub version: String,
+
+    /// Imports rewired or stubbed during the conversion: (module name, function name).
+    pub replaced: Vec<(String, String)>,
+}
+
+impl ConversionInfo {
+    /// Record of the conversion described by the report, done by the current tool version.
+    ///
+    /// The imports replaced by an earlier conversion of the module are kept in the record.
+    pub(crate) fn from_report(report: &ConversionReport) -> ConversionInfo {
+        let mut replaced: Vec<(String, String)> = report
+            .previous_conversion
+            .as_ref()
+            .map(|info| info.replaced.clone())
+            .unwrap_or_default();
+
+        let converted = report
+            .replaced()
+            .map(|import| (import.module.clone(), import.name.clone()));
+
+        for import in converted {
+            if !replaced.contains(&import) {
+                replaced.push(import);
+            }
+        }
+
+        ConversionInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            replaced,
+        }
+    }
+
+    /// returns the record as the JSON value stored in the custom section
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "replaced": self.replaced.iter()
+                .map(|(module, name)| json!({ "module": module, "name": name }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<ConversionInfo> {
+        let version = value.get("version")?.as_str()?.to_string();
+
+        let replaced = value
+            .get("replaced")?
+            .as_array()?
+            .iter()
+            .map(|import| {
+                Some((
+                    import.get("module")?.as_str()?.to_string(),
+                    import.get("name")?.as_str()?.to_string(),
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(ConversionInfo { version, replaced })
+    }
+
+    /// returns the recorded imports that are still imported by the module,
+    /// the list is empty if the module is consistent with the record
+    pub(crate) fn still_imported(&self, module: &walrus::Module) -> Vec<(String, String)> {
+        self.replaced
+            .iter()
+            .filter(|(module_name, name)| module.imports.find(module_name, name).is_some())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Read the conversion record of the module.
+///
+/// returns None if the module was not converted by wasi2ic, fails if the record cannot be parsed
+pub(crate) fn read_info(module: &walrus::Module) -> Result<Option<ConversionInfo>, Wasi2icError> {
+    let Some((_, section)) = module
+        .customs
+        .iter()
+        .find(|(_, section)| section.name() == INFO_SECTION_NAME)
+    else {
+        return Ok(None);
+    };
+
+    let data = section.data(&walrus::IdsToIndices::default());
+
+    serde_json::from_slice(&data)
+        .ok()
+        .as_ref()
+        .and_then(ConversionInfo::from_json)
+        .map(Some)
+        .ok_or_else(|| {
+            Wasi2icError::Parse(format!(
+                "Invalid '{INFO_SECTION_NAME}' custom section in the module"
+            ))
+        })
+}
+
+/// Store the conversion record in the module, replacing the previous record.
+pub(crate) fn write_info(module: &mut walrus::Module, info: &ConversionInfo) {
+    module.customs.remove_raw(INFO_SECTION_NAME);
+
+    module.customs.add(walrus::RawCustomSection {
+        name: INFO_SECTION_NAME.to_string(),
+        data: info.to_json().to_string().into_bytes(),
+    });
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+use std::str::FromStr;
+
+use walrus::{ExportItem, FunctionBuilder, FunctionId};
+
+use crate::error::Wasi2icError;
+use crate::options::{Options, StubMode};
+use crate::report::{ChainedStartReport, InitHookReport, Signature};
+
+/// Export called by the Internet Computer when the canister is installed.
+pub const CANISTER_INIT: &str = "canister_init";
+
+/// Export called by the Internet Computer after the canister is upgraded.
+pub const CANISTER_POST_UPGRADE: &str = "canister_post_upgrade";
+
+/// Initializer function of the reactor modules, called before the other exports.
+pub const INITIALIZE: &str = "_initialize";
+
+/// Entry point of the WASI command modules, runs the `main` function of the program.
+pub const START: &str = "_start";
+
+/// Import terminating the WASI command with an exit code.
+const PROC_EXIT: &str = "proc_exit";
+
+/// Way the initialization function, `_initialize` by default, is called in the converted module.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InitMode {
+    /// `_initialize` becomes the module start function.
+    #[default]
+    Start,
+
+    /// `_initialize` is called at the beginning of the `canister_init` and `canister_post_upgrade` exports,
+    /// the exports are generated if missing. The System API can be used during the initialization.
+    Hooks,
+}
+
+impl fmt::Display for InitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitMode::Start => write!(f, "start"),
+            InitMode::Hooks => write!(f, "hooks"),
+        }
+    }
+}
+
+impl FromStr for InitMode {
+    type Err = Wasi2icError;
+
+    /// Parse the initialization mode: `start` or `hooks`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(InitMode::Start),
+            "hooks" => Ok(InitMode::Hooks),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown init mode '{s}', expected one of: start, hooks"
+            ))),
+        }
+    }
+}
+
+/// Handling of the WASI command modules, which export `_start` instead of an initializer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandMode {
+    /// The conversion fails.
+    Reject,
+
+    /// `_start` becomes the initializer, the `proc_exit` import gets a trapping stub if there is no replacement,
+    /// so that a non-zero exit code fails the initialization.
+    Init,
+}
+
+impl fmt::Display for CommandMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandMode::Reject => write!(f, "reject"),
+            CommandMode::Init => write!(f, "init"),
+        }
+    }
+}
+
+impl FromStr for CommandMode {
+    type Err = Wasi2icError;
+
+    /// Parse the command handling: `reject` or `init`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(CommandMode::Reject),
+            "init" => Ok(CommandMode::Init),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown command mode '{s}', expected one of: reject, init"
+            ))),
+        }
+    }
+}
+
+/// Apply the command handling to the options, if the module is a WASI command exporting `_start`.
+///
+/// returns the options to convert the command with, None if the options are used unchanged,
+/// fails if the commands are rejected
+pub(crate) fn command_options(
+    module: &walrus::Module,
+    options: &Options,
+) -> Result<Option<Options>, Wasi2icError> {
+    if module.exports.get_func(START).is_err() {
+        return Ok(None);
+    }
+
+    match options.command {
+        None => Ok(None),
+        Some(CommandMode::Reject) => Err(Wasi2icError::CommandModule {
+            entry: START.to_string(),
+        }),
+        Some(CommandMode::Init) => {
+            log::info!("Converting the WASI command: {START} becomes the initializer");
+
+            let mut options = options.clone();
+
+            options.initializers.retain(|name| name != START);
+            options.initializers.insert(0, START.to_string());
+
+            options
+                .import_stubs
+                .entry(PROC_EXIT.to_string())
+                .or_insert(StubMode::Trap);
+
+            Ok(Some(options))
+        }
+    }
+}
+
+/// Find the initializer function, the candidates are tried in order by the function name, then by the export name.
+/// Candidates not of the type `() -> ()` cannot be called as the initializer and are skipped.
+///
+/// returns the candidate name and the function found, None if none of the candidates exists,
+/// fails if a candidate other than the WASI entry points `_initialize` and `_start` has a wrong type,
+/// as it was named explicitly by the options
+pub(crate) fn find_initializer(
+    module: &walrus::Module,
+    candidates: &[String],
+) -> Result<Option<(String, FunctionId)>, Wasi2icError> {
+    for name in candidates {
+        let Some(initializer) = module
+            .funcs
+            .by_name(name)
+            .or_else(|| module.exports.get_func(name).ok())
+        else {
+            continue;
+        };
+
+        let signature = Signature::from_type(module, module.funcs.get(initializer).ty());
+
+        if signature.params.is_empty() && signature.results.is_empty() {
+            return Ok(Some((name.clone(), initializer)));
+        }
+
+        if name != INITIALIZE && name != START {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "The initializer '{name}' must have the type () -> (), found {signature}"
+            )));
+        }
+
+        log::warn!(
+            "Skipping the initializer candidate {name} of the type {signature}, expected () -> ()"
+        );
+    }
+
+    Ok(None)
+}
+
+/// Order of the calls in the start function combining `_initialize` with the existing start function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartOrder {
+    /// `_initialize` is called before the original start function.
+    #[default]
+    InitializeFirst,
+
+    /// `_initialize` is called after the original start function.
+    StartFirst,
+}
+
+impl fmt::Display for StartOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartOrder::InitializeFirst => write!(f, "initialize-first"),
+            StartOrder::StartFirst => write!(f, "start-first"),
+        }
+    }
+}
+
+impl FromStr for StartOrder {
+    type Err = Wasi2icError;
+
+    /// Parse the call order: `initialize-first` or `start-first`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "initialize-first" => Ok(StartOrder::InitializeFirst),
+            "start-first" => Ok(StartOrder::StartFirst),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown start order '{s}', expected one of: initialize-first, start-first"
+            ))),
+        }
+    }
+}
+
+/// Replace the existing start function by a function calling both the initializer and the original start function
+/// in the given order.
+///
+/// returns the original start function chained, None if there is no start function
+/// or the start function is the initializer already
+pub(crate) fn chain_start_function(
+    module: &mut walrus::Module,
+    initialize: FunctionId,
+    order: StartOrder,
+) -> Option<ChainedStartReport> {
+    let start = module.start?;
+
+    if start == initialize {
+        return None;
+    }
+
+    let original = module
+        .funcs
+        .get(start)
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("func{}", start.index()));
+
+    log::info!("Chaining the initializer with the start function {original} ({order})");
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &[], &[]);
+    builder.name("__wasi2ic_start".to_string());
+
+    let calls = match order {
+        StartOrder::InitializeFirst => [initialize, start],
+        StartOrder::StartFirst => [start, initialize],
+    };
+
+    let mut body = builder.func_body();
+    for call in calls {
+        body.call(call);
+    }
+
+    module.start = Some(builder.finish(Vec::new(), &mut module.funcs));
+
+    Some(ChainedStartReport { original, order })
+}
+
+/// Generate the function calling `initialize` and then `hook`, if given, with the arguments of the hook.
+fn generate_hook(
+    module: &mut walrus::Module,
+    export_name: &str,
+    initialize: FunctionId,
+    hook: Option<FunctionId>,
+) -> FunctionId {
+    let (params, results) = match hook {
+        Some(hook) => {
+            let ty = module.types.get(module.funcs.get(hook).ty());
+            (ty.params().to_vec(), ty.results().to_vec())
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let args: Vec<walrus::LocalId> = params.iter().map(|ty| module.locals.add(*ty)).collect();
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    builder.name(format!("__wasi2ic_{export_name}"));
+
+    let mut body = builder.func_body();
+    body.call(initialize);
+
+    if let Some(hook) = hook {
+        for arg in &args {
+            body.local_get(*arg);
+        }
+
+        body.call(hook);
+    }
+
+    builder.finish(args, &mut module.funcs)
+}
+
+/// Call the initializer at the beginning of the `canister_init` and `canister_post_upgrade` exports,
+/// the exports are wrapped into a function doing the call, or generated if missing.
+///
+/// returns the exports wrapped or generated
+pub(crate) fn add_init_hooks(
+    module: &mut walrus::Module,
+    initialize: FunctionId,
+) -> Vec<InitHookReport> {
+    let mut hooks = Vec::new();
+
+    for export_name in [CANISTER_INIT, CANISTER_POST_UPGRADE] {
+        let export = module
+            .exports
+            .iter()
+            .find(|export| export.name == export_name)
+            .map(|export| (export.id(), export.item));
+
+        match export {
+            Some((export_id, ExportItem::Function(hook))) => {
+                log::info!("Calling the initializer at the beginning of {export_name}");
+
+                let wrapper = generate_hook(module, export_name, initialize, Some(hook));
+                module.exports.get_mut(export_id).item = ExportItem::Function(wrapper);
+
+                hooks.push(InitHookReport {
+                    export: export_name.to_string(),
+                    synthesized: false,
+                });
+            }
+            Some(_) => {
+                log::warn!("Export {export_name} is not a function; leaving it unchanged");
+            }
+            None => {
+                log::info!("Generating {export_name} calling the initializer");
+
+                let hook = generate_hook(module, export_name, initialize, None);
+                module.exports.add(export_name, hook);
+
+                hooks.push(InitHookReport {
+                    export: export_name.to_string(),
+                    synthesized: true,
+                });
+            }
+        }
+    }
+
+    hooks
+}
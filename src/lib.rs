@@ -1,17 +1,170 @@
+mod adapters;
 mod common;
+mod compression;
+mod error;
+mod ic0_signatures;
+mod info;
+mod init;
+mod limits;
+mod mapping;
+mod metadata;
+mod optimize;
+mod options;
+mod parse;
+mod report;
+mod resolver;
+mod strip;
+mod stubs;
+mod wasi_signatures;
+
+pub use adapters::AdapterRule;
+pub use error::Wasi2icError;
+pub use info::{ConversionInfo, INFO_SECTION_NAME};
+pub use init::{
+    CommandMode, InitMode, StartOrder, CANISTER_INIT, CANISTER_POST_UPGRADE, INITIALIZE, START,
+};
+pub use limits::{LimitViolation, LimitsProfile, LIMITS_PROFILES};
+pub use mapping::ImportMapping;
+pub use metadata::{Metadata, MetadataVisibility, CANDID_SERVICE};
+pub use optimize::OptimizeLevel;
+pub use options::{CheckOptions, Options, StubMode, ENOSYS};
+pub use report::{
+    ChainedStartReport, CheckReport, ConversionReport, Ic0ImportIssue, ImportReport,
+    ImportResolution, InitHookReport, OptimizeReport, Signature, StripReport, StubReport,
+};
+pub use resolver::{PrefixResolver, ReplacementResolver, DEFAULT_PREFIX, DEFAULT_TEMPLATE};
+pub use strip::StripSection;
+
+/// Result of the [`convert_bytes`] conversion.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// The converted module in the binary Wasm format.
+    pub wasm: Vec<u8>,
+
+    /// Resolution outcome for each function import.
+    pub report: ConversionReport,
+}
+
+impl Output {
+    /// returns the converted module compressed with gzip, the compression is deterministic
+    pub fn gzipped_wasm(&self) -> Result<Vec<u8>, Wasi2icError> {
+        compression::gzip(&self.wasm)
+    }
+}
+
+/// Parse the module given as bytes: binary Wasm, Wasm text or either of them compressed with gzip.
+/// The format is detected from the content.
+///
+/// returns the module in the binary Wasm format together with the parsed module,
+/// the DWARF sections are only kept in the parsed module if `keep_dwarf` is set
+pub(crate) fn parse_bytes(
+    input: &[u8],
+    keep_dwarf: bool,
+) -> Result<(Vec<u8>, walrus::Module), Wasi2icError> {
+    let wasm = parse::decode_module(input)?;
+    let module = parse::parse_wasm(&wasm, keep_dwarf)?;
+
+    Ok((wasm, module))
+}
+
+/// Rewire WASI functions of the module given as bytes: binary Wasm, Wasm text or either of them compressed with gzip.
+/// The format is detected from the content.
+///
+/// A module already converted by wasi2ic is returned unchanged, unless `reconvert` is set in the options.
+///
+/// returns the converted module in the binary Wasm format together with the conversion report,
+/// fails if the module cannot be parsed or the import mapping given in the options cannot be applied
+pub fn convert_bytes(input: &[u8], options: &Options) -> Result<Output, Wasi2icError> {
+    let (wasm, mut module) = parse_bytes(input, options.keep_dwarf)?;
+
+    let report = common::do_module_replacements(&mut module, options)?;
+
+    // avoid rewriting a module converted before
+    let wasm = if report.previous_conversion.is_some() && !report.modified {
+        wasm
+    } else {
+        module.emit_wasm()
+    };
+
+    Ok(Output { wasm, report })
+}
 
 /// Rewire WASI functions.
 /// If there are no functions found for replacement, the module processing will not happen.
 /// This is done to avoid any modification if the ic-wasi-polyfill library was not included in the build.
 ///
-/// returns true if the module was modified
+/// returns true if the module was modified, errors are logged and reported as false,
+/// use [`convert_module`] to get the error instead
 pub fn process_module(m: &mut walrus::Module) -> bool {
-    common::do_module_replacements(m)
+    match common::do_module_replacements(m, &Options::default()) {
+        Ok(report) => report.modified,
+        Err(e) => {
+            log::error!("{e}");
+            false
+        }
+    }
+}
+
+/// Rewire WASI functions, same as [`process_module`].
+///
+/// returns the report listing the resolution outcome for each function import
+pub fn convert_module(m: &mut walrus::Module) -> Result<ConversionReport, Wasi2icError> {
+    common::do_module_replacements(m, &Options::default())
+}
+
+/// Rewire WASI functions using the conversion options provided.
+///
+/// returns the report listing the resolution outcome for each function import,
+/// fails if the import mapping given in the options cannot be applied
+pub fn convert_module_with_options(
+    m: &mut walrus::Module,
+    options: &Options,
+) -> Result<ConversionReport, Wasi2icError> {
+    common::do_module_replacements(m, options)
+}
+
+/// Check the module given as bytes for the compatibility with the Internet Computer: list its imports, check the
+/// `ic0` imports against the System API specification and the module against the limits, as set by the options.
+/// The module format is detected from the content, as in [`convert_bytes`], it is parsed once for all the checks.
+///
+/// returns the imports, the `ic0` import issues and the limits exceeded, fails if the module cannot be parsed
+pub fn check_bytes(input: &[u8], options: &CheckOptions) -> Result<CheckReport, Wasi2icError> {
+    let (wasm, module) = parse_bytes(input, false)?;
+
+    let ic0_issues = if options.skip_ic0_check {
+        Vec::new()
+    } else {
+        ic0_signatures::check_ic0_imports(&module, options.strict_ic0)
+    };
+
+    // the section sizes are measured in the binary Wasm format
+    let limit_violations = match &options.limits_profile {
+        Some(profile) => limits::check_limits(&module, &wasm, profile)?,
+        None => Vec::new(),
+    };
+
+    Ok(CheckReport {
+        imports: common::get_module_imports(&module),
+        ic0_issues,
+        limit_violations,
+    })
 }
 
 /// Convenience function to get the list of functions imported
 ///
 /// returns pairs of values: (module name, function name)
-pub fn module_imports(m: &mut walrus::Module) -> Vec<(String, String)> {
+pub fn module_imports(m: &walrus::Module) -> Vec<(String, String)> {
     common::get_module_imports(m)
 }
+
+/// Same as [`module_imports`] for the module given as bytes, the format is detected from the content.
+///
+/// returns pairs of values: (module name, function name), fails if the module cannot be parsed
+pub fn module_imports_from_bytes(input: &[u8]) -> Result<Vec<(String, String)>, Wasi2icError> {
+    let (_, module) = parse_bytes(input, false)?;
+
+    Ok(common::get_module_imports(&module))
+}
+
+#[cfg(test)]
+mod lib_tests;
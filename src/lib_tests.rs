@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use crate::options::Options;
+use crate::*;
+
+#[test]
+fn test_add_start_entry() {
+    let wat = r#"
+        (module
+            (func $add (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add
+            )
+
+            (func $_initialize
+                i32.const 2
+                i32.const 3
+                call $add
+                i32.const 5
+                i32.const 7
+                call $add
+                drop
+                drop
+            )
+        )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    assert!(module.start.is_none());
+
+    let initializer = module.funcs.by_name("_initialize").unwrap();
+    common::add_start_entry(&mut module, initializer);
+
+    assert!(module.start.is_some());
+}
+
+#[test]
+fn test_remove_start_export() {
+    let wat = r#"
+        (module
+            (func $add (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add
+            )
+
+            (func $_initialize
+                i32.const 2
+                i32.const 3
+                call $add
+                i32.const 5
+                i32.const 7
+                call $add
+                drop
+                drop
+            )
+
+            (export "_initialize" (func $_initialize))
+        )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let mut export_found: Option<walrus::ExportId> = None;
+
+    // try to find the initialize export
+    for export in module.exports.iter() {
+        if !export.name.starts_with("_initialize") {
+            continue;
+        }
+
+        if let walrus::ExportItem::Function(_) = export.item {
+            export_found = Some(export.id());
+        }
+    }
+
+    assert!(export_found.is_some());
+
+    common::remove_start_export(&mut module, "_initialize");
+
+    let mut export_found: Option<walrus::ExportId> = None;
+    // try to find the initialize export
+    for export in module.exports.iter() {
+        if !export.name.starts_with("_initialize") {
+            continue;
+        }
+
+        if let walrus::ExportItem::Function(_) = export.item {
+            export_found = Some(export.id());
+        }
+    }
+
+    assert!(export_found.is_none());
+}
+
+#[test]
+fn test_gather_replacement_ids() {
+    let wat = r#"
+    (module
+        (type (;0;) (func))
+        (type (;1;) (func (param i32)))
+        (type (;2;) (func (param i32 i32)))
+        (type (;3;) (func (param i32 i32) (result i32)))
+        (type (;4;) (func (param i32 i32 i32)))
+        (type (;5;) (func (param i32 i32 i32 i32) (result i32)))
+
+        (import "ic0" "debug_print" (func $_dprint (;0;) (type 2)))
+        (import "ic0" "msg_reply" (func $_msg_reply (;1;) (type 0)))
+        (import "wasi_unstable" "fd_write" (func $_wasi_unstable_fd_write (;2;) (type 5)))
+        (import "wasi_unstable" "random_get" (func $_wasi_unstable_random_get (;3;) (type 3)))
+        (import "wasi_unstable" "environ_get" (func $__imported_wasi_unstable_environ_get (;4;) (type 3)))
+        (import "wasi_unstable" "proc_exit" (func $__imported_wasi_unstable_proc_exit (;5;) (type 1)))
+
+        (func $_initialize (;6;) (type 0)
+            i32.const 1
+            i32.const 2
+            call $__ic_custom_random_get
+            i32.const 1
+            i32.const 2
+            call $_wasi_unstable_random_get
+            i32.const 4
+            i32.const 5
+            call $_wasi_unstable_fd_write
+            drop
+        )
+
+        (func $__ic_custom_random_get (;8;) (type 3) (param i32 i32) (result i32)
+            call $_msg_reply
+
+            i32.const 421
+        )
+
+        (func $ic_dummy_fd_write (;7;) (type 5) (param i32 i32 i32 i32) (result i32)
+            i32.const 0
+            i32.const 0
+            call $_dprint
+            i32.const 42
+        )
+
+        (export "__ic_custom_fd_write" (func $ic_dummy_fd_write))
+        (export "_initialize" (func $_initialize))
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let id_reps: HashMap<usize, usize> =
+        common::gather_replacement_ids(&module, &Options::default())
+            .unwrap()
+            .ids
+            .iter()
+            .map(|(x, y)| (x.index(), y.index()))
+            .collect();
+
+    assert!(id_reps[&2] == 8);
+    assert!(id_reps[&3] == 7);
+}
+
+#[test]
+fn test_conversion_info() {
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+    let options = crate::Options::default();
+
+    let output = crate::convert_bytes(&wat, &options).unwrap();
+    assert!(output.report.previous_conversion.is_none());
+
+    let mut module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    let recorded = info::read_info(&module).unwrap().unwrap();
+
+    assert_eq!(recorded.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        recorded.replaced,
+        output
+            .report
+            .replaced()
+            .map(|import| (import.module.clone(), import.name.clone()))
+            .collect::<Vec<_>>()
+    );
+
+    // the converted module is detected and returned byte for byte
+    let again = crate::convert_bytes(&output.wasm, &options).unwrap();
+    assert!(!again.report.modified);
+    assert!(again.report.imports.is_empty());
+    assert_eq!(
+        again
+            .report
+            .previous_conversion
+            .as_ref()
+            .map(|info| &info.version),
+        Some(&recorded.version)
+    );
+    assert_eq!(again.wasm, output.wasm);
+
+    // the module is verified against the record
+    let mut inconsistent = recorded.clone();
+    inconsistent
+        .replaced
+        .push(("ic0".to_string(), "debug_print".to_string()));
+    info::write_info(&mut module, &inconsistent);
+
+    let result = common::do_module_replacements(&mut module, &Options::default());
+    let Err(error::Wasi2icError::InconsistentConversion { imports, .. }) = result else {
+        panic!("expected inconsistent conversion, got {result:?}");
+    };
+    assert_eq!(
+        imports,
+        vec![("ic0".to_string(), "debug_print".to_string())]
+    );
+
+    let options = Options {
+        reconvert: true,
+        ..Default::default()
+    };
+    let report = common::do_module_replacements(&mut module, &options).unwrap();
+    assert!(report.previous_conversion.is_some());
+}
+
+#[test]
+fn test_convert_bytes() {
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+    let options = crate::Options::default();
+
+    let output = crate::convert_bytes(&wat, &options).unwrap();
+
+    assert!(output.report.modified);
+    assert_eq!(output.report.replaced().count(), 2);
+
+    let module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    assert!(module.imports.find("ic0", "debug_print").is_some());
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "fd_write")
+        .is_none());
+
+    // the converted module is accepted again in both the binary and the compressed form
+    let gzipped = output.gzipped_wasm().unwrap();
+    assert_eq!(gzipped, output.gzipped_wasm().unwrap());
+
+    for input in [&output.wasm, &gzipped] {
+        let again = crate::convert_bytes(input, &options).unwrap();
+        assert!(!again.report.modified);
+    }
+
+    assert!(crate::convert_bytes(b"(module", &options).is_err());
+}
+
+#[test]
+fn test_strip_sections() {
+    let wasm = main_test_with_custom_sections();
+
+    let output = crate::convert_bytes(&wasm, &crate::Options::default()).unwrap();
+    assert!(output.report.stripped.is_none());
+    assert!(custom_section_names(&output.wasm).contains(&"name".to_string()));
+    assert!(custom_section_names(&output.wasm).contains(&"target_features".to_string()));
+
+    let strip = |strip: &[crate::StripSection]| {
+        let options = crate::Options {
+            strip: strip.to_vec(),
+            ..Default::default()
+        };
+        crate::convert_bytes(&wasm, &options).unwrap()
+    };
+
+    let names = strip(&[crate::StripSection::Names]);
+    let stripped = names.report.stripped.as_ref().unwrap();
+    assert_eq!(
+        stripped
+            .sections
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["name"]
+    );
+    assert_eq!(
+        stripped.bytes_saved as usize,
+        output.wasm.len() - names.wasm.len()
+    );
+    assert!(!custom_section_names(&names.wasm).contains(&"name".to_string()));
+    assert!(custom_section_names(&names.wasm).contains(&"target_features".to_string()));
+
+    // the icp metadata and the conversion record are kept
+    let all = strip(&[crate::StripSection::All]);
+    assert_eq!(
+        custom_section_names(&all.wasm),
+        vec!["icp:public candid:service", crate::INFO_SECTION_NAME]
+    );
+    assert!(all.report.stripped.unwrap().bytes_saved > stripped.bytes_saved);
+
+    // the module without imports to replace is stripped too
+    let again = crate::convert_bytes(
+        &all.wasm,
+        &crate::Options {
+            strip: vec![crate::StripSection::All],
+            reconvert: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(!again.report.modified);
+    assert!(again.report.stripped.unwrap().sections.is_empty());
+
+    assert_eq!(
+        "target-features".parse::<crate::StripSection>().unwrap(),
+        crate::StripSection::TargetFeatures
+    );
+    assert!("symbols".parse::<crate::StripSection>().is_err());
+
+    let options = crate::Options {
+        keep_dwarf: true,
+        strip: vec![crate::StripSection::Dwarf],
+        ..Default::default()
+    };
+    assert!(matches!(
+        crate::convert_bytes(&wasm, &options),
+        Err(crate::Wasi2icError::InvalidOption(_))
+    ));
+}
+
+#[test]
+fn test_optimize_module() {
+    assert_eq!(
+        "shrink".parse::<crate::OptimizeLevel>().unwrap(),
+        crate::OptimizeLevel::Shrink
+    );
+    assert_eq!(
+        "Oz".parse::<crate::OptimizeLevel>().unwrap(),
+        crate::OptimizeLevel::Oz
+    );
+    assert_eq!(crate::OptimizeLevel::O3.to_string(), "O3");
+    assert!(matches!(
+        "O5".parse::<crate::OptimizeLevel>(),
+        Err(crate::Wasi2icError::InvalidOption(_))
+    ));
+
+    let wasm = main_test_with_custom_sections();
+
+    let output = crate::convert_bytes(&wasm, &crate::Options::default()).unwrap();
+    assert!(output.report.optimized.is_none());
+
+    for level in [crate::OptimizeLevel::Shrink, crate::OptimizeLevel::Oz] {
+        let options = crate::Options {
+            optimize: Some(level),
+            ..Default::default()
+        };
+
+        let output = crate::convert_bytes(&wasm, &options).unwrap();
+        let optimized = output.report.optimized.unwrap();
+        assert_eq!(optimized.level, level.to_string());
+        assert!(optimized.size_after < optimized.size_before);
+
+        wasmparser::Validator::new()
+            .validate_all(&output.wasm)
+            .unwrap();
+
+        let sections = custom_section_names(&output.wasm);
+        assert!(!sections.contains(&"target_features".to_string()));
+        assert!(sections.contains(&"icp:public candid:service".to_string()));
+        assert!(sections.contains(&crate::INFO_SECTION_NAME.to_string()));
+
+        // the conversion record is kept, so the optimized module is not converted again
+        let again = crate::convert_bytes(&output.wasm, &crate::Options::default()).unwrap();
+        assert!(again.report.previous_conversion.is_some());
+    }
+
+    // the size before the optimization is the size of the module stripped and with the metadata replaced
+    let mut options = crate::Options {
+        strip: vec![crate::StripSection::Names],
+        metadata: vec!["candid:service=service : { go : () -> () }"
+            .parse()
+            .unwrap()],
+        ..Default::default()
+    };
+    let stripped = crate::convert_bytes(&output.wasm, &options).unwrap();
+
+    options.optimize = Some(crate::OptimizeLevel::Shrink);
+    let optimized = crate::convert_bytes(&output.wasm, &options).unwrap();
+    assert_eq!(
+        optimized.report.optimized.unwrap().size_before,
+        stripped.wasm.len() as u64
+    );
+
+    let options = crate::Options {
+        optimize: Some(crate::OptimizeLevel::O2),
+        keep_dwarf: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        crate::convert_bytes(&wasm, &options),
+        Err(crate::Wasi2icError::InvalidOption(_))
+    ));
+}
+
+/// returns the main test module with a `target_features` section and the Candid metadata added
+fn main_test_with_custom_sections() -> Vec<u8> {
+    let mut wat = std::fs::read_to_string("test/assets/main_test.wat").unwrap();
+    let end = wat.rfind(')').unwrap();
+    wat.insert_str(
+        end,
+        r#"(@custom "target_features" "\01+\0amultivalue")
+(@custom "icp:public candid:service" "service : {}")
+"#,
+    );
+    wat::parse_str(&wat).unwrap()
+}
+
+/// returns the names of the custom sections in the module binary
+fn custom_section_names(wasm: &[u8]) -> Vec<String> {
+    wasmparser::Parser::new(0)
+        .parse_all(wasm)
+        .filter_map(|payload| match payload.unwrap() {
+            wasmparser::Payload::CustomSection(reader) => Some(reader.name().to_string()),
+            _ => None,
+        })
+        .collect()
+}
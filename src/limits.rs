@@ -0,0 +1,185 @@
+use std::fmt;
+
+use wasmparser::{Parser, Payload};
+
+use crate::error::Wasi2icError;
+
+/// Limits enforced by the Internet Computer on the installed Wasm modules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitsProfile {
+    /// Profile name used to select it from the command line.
+    pub name: &'static str,
+
+    /// Profile version, increased whenever the limits are updated.
+    pub version: u32,
+
+    /// Maximum number of functions, including the imported ones.
+    pub max_functions: u64,
+
+    /// Maximum number of globals, including the imported ones.
+    pub max_globals: u64,
+
+    /// Maximum number of exported functions.
+    pub max_exported_functions: u64,
+
+    /// Maximum total length of the exported function names.
+    pub max_exported_function_names_length: u64,
+
+    /// Maximum number of the `icp:` custom sections.
+    pub max_custom_sections: u64,
+
+    /// Maximum total size of the `icp:` custom sections in bytes.
+    pub max_custom_sections_size: u64,
+
+    /// Maximum number of data segments.
+    pub max_data_segments: u64,
+
+    /// Maximum size of the code section in bytes.
+    pub max_code_section_size: u64,
+
+    /// Maximum size of the uncompressed module in bytes.
+    pub max_module_size: u64,
+}
+
+/// Built-in limits profiles, the last one is used by default.
+pub const LIMITS_PROFILES: &[LimitsProfile] = &[LimitsProfile {
+    name: "ic-v1",
+    version: 1,
+    max_functions: 50_000,
+    max_globals: 1_000,
+    max_exported_functions: 1_000,
+    max_exported_function_names_length: 20_000,
+    max_custom_sections: 16,
+    max_custom_sections_size: 1 << 20,
+    max_data_segments: 10_000,
+    max_code_section_size: 11 << 20,
+    max_module_size: 100 << 20,
+}];
+
+impl LimitsProfile {
+    /// returns the built-in profile with the given name
+    pub fn find(name: &str) -> Option<&'static LimitsProfile> {
+        LIMITS_PROFILES.iter().find(|profile| profile.name == name)
+    }
+
+    /// returns the most recent built-in profile
+    pub fn latest() -> &'static LimitsProfile {
+        LIMITS_PROFILES
+            .last()
+            .expect("at least one limits profile is defined")
+    }
+}
+
+/// Module property exceeding its limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitViolation {
+    /// Name of the limit, e.g. `functions`.
+    pub limit: &'static str,
+
+    /// Value measured in the module.
+    pub measured: u64,
+
+    /// Maximum value allowed.
+    pub maximum: u64,
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} exceeds the limit of {}",
+            self.limit, self.measured, self.maximum
+        )
+    }
+}
+
+/// Check the module against the limits of the profile.
+///
+/// `wasm` is the emitted module binary, used to measure the section sizes.
+///
+/// returns the violations found, or an error if the binary cannot be parsed
+pub(crate) fn check_limits(
+    module: &walrus::Module,
+    wasm: &[u8],
+    profile: &LimitsProfile,
+) -> Result<Vec<LimitViolation>, Wasi2icError> {
+    let mut custom_sections = 0u64;
+    let mut custom_sections_size = 0u64;
+    let mut data_segments = 0u64;
+    let mut code_section_size = 0u64;
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload.map_err(|e| Wasi2icError::Parse(e.to_string()))? {
+            Payload::CustomSection(reader) if reader.name().starts_with("icp:") => {
+                custom_sections += 1;
+                custom_sections_size += (reader.name().len() + reader.data().len()) as u64;
+            }
+            Payload::DataSection(reader) => {
+                data_segments += reader.count() as u64;
+            }
+            Payload::CodeSectionStart { size, .. } => {
+                code_section_size += size as u64;
+            }
+            _ => {}
+        }
+    }
+
+    let exported_functions: Vec<&walrus::Export> = module
+        .exports
+        .iter()
+        .filter(|export| matches!(export.item, walrus::ExportItem::Function(_)))
+        .collect();
+
+    let measured = [
+        (
+            "functions",
+            module.funcs.iter().count() as u64,
+            profile.max_functions,
+        ),
+        (
+            "globals",
+            module.globals.iter().count() as u64,
+            profile.max_globals,
+        ),
+        (
+            "exported functions",
+            exported_functions.len() as u64,
+            profile.max_exported_functions,
+        ),
+        (
+            "exported function names length",
+            exported_functions
+                .iter()
+                .map(|export| export.name.len() as u64)
+                .sum(),
+            profile.max_exported_function_names_length,
+        ),
+        (
+            "icp custom sections",
+            custom_sections,
+            profile.max_custom_sections,
+        ),
+        (
+            "icp custom sections size",
+            custom_sections_size,
+            profile.max_custom_sections_size,
+        ),
+        ("data segments", data_segments, profile.max_data_segments),
+        (
+            "code section size",
+            code_section_size,
+            profile.max_code_section_size,
+        ),
+        ("module size", wasm.len() as u64, profile.max_module_size),
+    ];
+
+    Ok(measured
+        .into_iter()
+        .filter(|(_, measured, maximum)| measured > maximum)
+        .map(|(limit, measured, maximum)| LimitViolation {
+            limit,
+            measured,
+            maximum,
+        })
+        .collect())
+}
@@ -1,8 +1,60 @@
 mod arguments;
-mod common;
-use crate::{arguments::Wasm2icArgs, common::get_module_imports};
+mod batch;
+mod output;
+use crate::arguments::{OutputFormat, Wasm2icArgs};
 use clap::Parser;
+use std::io::{Read, Write};
 use std::path::Path;
+use std::process::ExitCode;
+use wasi2ic::{
+    ConversionReport, Ic0ImportIssue, LimitViolation, StartOrder, Wasi2icError, INITIALIZE,
+};
+
+/// File name standing for the standard input or output.
+pub(crate) const STDIO: &str = "-";
+
+/// Result of checking the module compatibility with the Internet Computer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The module has no imports other than ic0, no conversion is needed.
+    Clean,
+    /// The module has only ic0 imports after the conversion.
+    Convertible,
+    /// The module has imports other than ic0 even after the conversion.
+    Unresolved,
+    /// The module ic0 imports do not match the Internet Computer System API.
+    InvalidIc0Imports,
+    /// The module imports are compatible, but the module exceeds the Internet Computer limits.
+    LimitsExceeded,
+    /// The input could not be read, parsed or converted.
+    InvalidInput,
+}
+
+impl CheckStatus {
+    /// Process exit code, codes 1 and 2 are avoided as they are used for general errors and usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CheckStatus::Clean => 0,
+            CheckStatus::Convertible => 3,
+            CheckStatus::Unresolved => 4,
+            CheckStatus::InvalidInput => 5,
+            CheckStatus::LimitsExceeded => 6,
+            CheckStatus::InvalidIc0Imports => 7,
+        }
+    }
+
+    /// Status name used in the JSON output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckStatus::Clean => "clean",
+            CheckStatus::Convertible => "convertible",
+            CheckStatus::Unresolved => "unresolved",
+            CheckStatus::InvalidInput => "invalid_input",
+            CheckStatus::LimitsExceeded => "limits_exceeded",
+            CheckStatus::InvalidIc0Imports => "invalid_ic0_imports",
+        }
+    }
+}
 
 fn is_wat(path: &Path) -> bool {
     if let Some(ext) = path.extension() {
@@ -14,76 +66,492 @@ fn is_wat(path: &Path) -> bool {
     false
 }
 
-pub fn show_module_imports(module: &walrus::Module) {
-    let imports = get_module_imports(module);
-    println!("Module imports:");
+fn is_gz(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        if ext == "gz" {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn show_module_imports(
+    out: &mut dyn Write,
+    imports: &[(String, String)],
+) -> std::io::Result<()> {
+    writeln!(out, "Module imports:")?;
     for (mname, fname) in imports {
-        println!("  import \"{mname}\" \"{fname}\"");
+        writeln!(out, "  import \"{mname}\" \"{fname}\"")?;
     }
+
+    Ok(())
 }
 
-//fn do_wasm_file_processing(input_wasm: &Path, output_wasm: &Path) -> Result<(), anyhow::Error> {
-pub fn do_wasm_file_processing(args: &Wasm2icArgs) -> Result<(), anyhow::Error> {
+pub fn show_conversion_report(
+    out: &mut dyn Write,
+    report: &ConversionReport,
+) -> std::io::Result<()> {
+    for import in report.unresolved() {
+        writeln!(
+            out,
+            "  unresolved \"{}\" \"{}\": {}",
+            import.module, import.name, import.resolution
+        )?;
+    }
+
+    if let Some(info) = &report.previous_conversion {
+        if !report.modified {
+            writeln!(
+                out,
+                "  module already converted by wasi2ic {}, left unchanged (use --reconvert to convert it again)",
+                info.version
+            )?;
+        } else if report.imports.is_empty() {
+            writeln!(
+                out,
+                "  module already converted by wasi2ic {}, imports not rewired again (use --reconvert to convert it again)",
+                info.version
+            )?;
+        }
+    }
+
+    if let Some(optimized) = &report.optimized {
+        writeln!(
+            out,
+            "  optimized ({}): {} -> {} bytes",
+            optimized.level, optimized.size_before, optimized.size_after
+        )?;
+    }
+
+    let initializer = report.initializer.as_deref().unwrap_or(INITIALIZE);
+
+    if let Some(chained) = &report.chained_start {
+        let order = match chained.order {
+            StartOrder::InitializeFirst => "after",
+            StartOrder::StartFirst => "before",
+        };
+
+        writeln!(
+            out,
+            "  start function \"{}\" chained, called {order} {initializer}",
+            chained.original
+        )?;
+    }
+
+    for hook in &report.init_hooks {
+        if hook.synthesized {
+            writeln!(out, "  generated \"{}\" calling {initializer}", hook.export)?;
+        } else {
+            writeln!(
+                out,
+                "  {initializer} called at the beginning of \"{}\"",
+                hook.export
+            )?;
+        }
+    }
+
+    for (name, size) in &report.metadata {
+        writeln!(out, "  embedded metadata section \"{name}\": {size} bytes")?;
+    }
+
+    if let Some(stripped) = &report.stripped {
+        for (name, size) in &stripped.sections {
+            writeln!(out, "  stripped section \"{name}\": {size} bytes")?;
+        }
+
+        writeln!(out, "  stripping saved {} bytes", stripped.bytes_saved)?;
+    }
+
+    for stub in &report.stubs {
+        writeln!(
+            out,
+            "  stub generated for \"{}\" \"{}\": {}",
+            stub.module, stub.name, stub.mode
+        )?;
+    }
+
+    for export in &report.removed_exports {
+        log::info!("Removed export: {export}");
+    }
+
+    log::info!(
+        "Replaced {} imports, start function set: {}",
+        report.replaced().count(),
+        report.start_function_set
+    );
+
+    Ok(())
+}
+
+/// Module checked for the compatibility with the Internet Computer.
+#[derive(Debug, Default)]
+pub struct ModuleCheck {
+    /// All function imports of the module.
+    pub imports: Vec<(String, String)>,
+
+    /// Imports from modules other than ic0.
+    pub remaining: Vec<(String, String)>,
+
+    /// Issues found with the ic0 imports.
+    pub ic0_issues: Vec<Ic0ImportIssue>,
+
+    /// Name and version of the limits profile used, None if the limits were not checked.
+    pub limits_profile: Option<(&'static str, u32)>,
+
+    /// Limits exceeded by the module.
+    pub limit_violations: Vec<LimitViolation>,
+}
+
+impl ModuleCheck {
+    /// Check the module given as bytes.
+    pub fn new(wasm: &[u8], args: &Wasm2icArgs) -> Result<ModuleCheck, Wasi2icError> {
+        let options = args.check_options()?;
+        let report = wasi2ic::check_bytes(wasm, &options)?;
+
+        Ok(ModuleCheck {
+            remaining: report
+                .imports
+                .iter()
+                .filter(|(mname, _fname)| mname != "ic0")
+                .cloned()
+                .collect(),
+            imports: report.imports,
+            ic0_issues: report.ic0_issues,
+            limits_profile: options
+                .limits_profile
+                .map(|profile| (profile.name, profile.version)),
+            limit_violations: report.limit_violations,
+        })
+    }
+
+    /// returns true if the Internet Computer accepts the module imports
+    pub fn is_compatible(&self) -> bool {
+        self.remaining.is_empty() && !self.has_ic0_errors()
+    }
+
+    /// returns true if some of the ic0 imports do not match the System API
+    pub fn has_ic0_errors(&self) -> bool {
+        self.ic0_issues.iter().any(|issue| issue.is_error())
+    }
+
+    /// returns true if the module is within the Internet Computer limits
+    pub fn is_within_limits(&self) -> bool {
+        self.limit_violations.is_empty()
+    }
+}
+
+pub fn show_module_check(
+    out: &mut dyn Write,
+    check: &ModuleCheck,
+    quiet: bool,
+) -> std::io::Result<()> {
+    if !check.is_compatible() {
+        show_module_imports(out, &check.imports)?;
+    }
+
+    for issue in &check.ic0_issues {
+        if issue.is_error() {
+            writeln!(out, "  invalid ic0 import: {issue}")?;
+        } else if !quiet {
+            writeln!(out, "  warning: {issue}")?;
+        }
+    }
+
+    for violation in &check.limit_violations {
+        writeln!(out, "  limit exceeded: {violation}")?;
+    }
+
+    Ok(())
+}
+
+/// Stream for the messages and reports: stderr if the converted module is written to the standard output.
+fn message_stream(args: &Wasm2icArgs) -> Box<dyn Write> {
+    if args.output_file == STDIO && !args.imports && !args.check {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    }
+}
+
+/// returns the content of the input file or the standard input
+fn read_input(input_file: &str) -> Result<Vec<u8>, Wasi2icError> {
+    let mut bytes = Vec::new();
+    if input_file == STDIO {
+        std::io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        bytes = std::fs::read(input_file)?;
+    }
+
+    Ok(bytes)
+}
+
+/// returns the parse error with the name of the input file the module was read from
+fn file_context(input_file: &str, e: Wasi2icError) -> Wasi2icError {
+    match e {
+        Wasi2icError::Parse(message) => Wasi2icError::Parse(format!("'{input_file}': {message}")),
+        e => e,
+    }
+}
+
+/// returns the function imports of the input module as pairs: (module name, function name)
+fn read_imports(input_file: &str) -> Result<Vec<(String, String)>, Wasi2icError> {
+    let bytes = read_input(input_file)?;
+
+    wasi2ic::module_imports_from_bytes(&bytes).map_err(|e| file_context(input_file, e))
+}
+
+/// returns the converted module in the binary Wasm format with the report
+fn convert_input(input: &[u8], args: &Wasm2icArgs) -> Result<wasi2ic::Output, Wasi2icError> {
+    wasi2ic::convert_bytes(input, &args.conversion_options()?)
+        .map_err(|e| file_context(&args.input_file, e))
+}
+
+/// Check if the module converts cleanly without writing any output.
+pub fn do_wasm_file_check(args: &Wasm2icArgs) -> CheckStatus {
+    let text = args.format == OutputFormat::Text;
+    let mut out = message_stream(args);
+
+    let mut check = || {
+        let input = read_input(&args.input_file)?;
+        let module_check =
+            ModuleCheck::new(&input, args).map_err(|e| file_context(&args.input_file, e))?;
+
+        if module_check.is_compatible() {
+            if !args.quiet && text {
+                show_module_check(&mut out, &module_check, args.quiet)?;
+            }
+            return Ok::<_, Wasi2icError>((None, module_check));
+        }
+
+        let converted = convert_input(&input, args)?;
+        let report = converted.report;
+
+        if !args.quiet && text {
+            show_conversion_report(&mut out, &report)?;
+        }
+
+        let module_check = ModuleCheck::new(&converted.wasm, args)?;
+
+        if !args.quiet && text {
+            show_module_check(&mut out, &module_check, args.quiet)?;
+        }
+
+        Ok((Some(report), module_check))
+    };
+
+    let result = check();
+
+    let status = match &result {
+        Ok((_, module_check)) if !module_check.remaining.is_empty() => CheckStatus::Unresolved,
+        Ok((_, module_check)) if module_check.has_ic0_errors() => CheckStatus::InvalidIc0Imports,
+        Ok((_, module_check)) if !module_check.is_within_limits() => CheckStatus::LimitsExceeded,
+        Ok((None, _)) => CheckStatus::Clean,
+        Ok((Some(_), _)) => CheckStatus::Convertible,
+        Err(e) => {
+            log::error!("Check failed: {e}");
+            CheckStatus::InvalidInput
+        }
+    };
+
+    match args.format {
+        OutputFormat::Text => {
+            if !args.quiet {
+                println!(
+                    "wasi2ic {}: checked input file '{}': {:?}",
+                    env!("CARGO_PKG_VERSION"),
+                    args.input_file,
+                    status
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let (report, module_check, error) = match &result {
+                Ok((report, module_check)) => (report.as_ref(), Some(module_check), None),
+                Err(e) => (None, None, Some(e.to_string())),
+            };
+
+            println!(
+                "{:#}",
+                output::check_document(
+                    &args.input_file,
+                    status.name(),
+                    status.exit_code(),
+                    report,
+                    module_check,
+                    error
+                )
+            );
+        }
+    }
+
+    status
+}
+
+//fn do_wasm_file_processing(input_wasm: &Path, output_wasm: &Path) -> Result<(), Wasi2icError> {
+pub fn do_wasm_file_processing(args: &Wasm2icArgs) -> Result<(), Wasi2icError> {
+    do_wasm_file_processing_with_output(args, &mut message_stream(args))
+}
+
+/// Same as [`do_wasm_file_processing`], printing the messages and reports into `out`.
+pub fn do_wasm_file_processing_with_output(
+    args: &Wasm2icArgs,
+    out: &mut dyn Write,
+) -> Result<(), Wasi2icError> {
     log::info!(
         "Processing input file: '{}', writing output into '{}'",
         args.input_file,
         args.output_file
     );
 
-    if !args.quiet && !args.imports {
-        println!(
+    if !args.quiet && !args.imports && args.format == OutputFormat::Text {
+        writeln!(
+            out,
             "wasi2ic {}: processing input file: '{}', writing output into '{}'",
             env!("CARGO_PKG_VERSION"),
             args.input_file,
             args.output_file
-        );
+        )?;
     }
 
-    let input_wasm = Path::new(&args.input_file);
-    let wasm = if is_wat(input_wasm) {
-        wat::parse_file(input_wasm)?
+    if args.imports {
+        let imports = read_imports(&args.input_file)?;
+
+        match args.format {
+            OutputFormat::Text => show_module_imports(out, &imports)?,
+            OutputFormat::Json => writeln!(
+                out,
+                "{:#}",
+                output::imports_document(&args.input_file, &imports)
+            )?,
+        }
     } else {
-        std::fs::read(input_wasm)?
-    };
+        let output_wasm = Path::new(&args.output_file);
+        if args.gzip && is_wat(output_wasm) {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "The Wasm text output '{}' cannot be compressed, remove --gzip or write a .wasm file",
+                args.output_file
+            )));
+        }
 
-    // use the same parser as dfx here
-    let mut module = ic_wasm::utils::parse_wasm(&wasm, true)?; //walrus::Module::from_buffer_with_config(&wasm, &config)?;
+        let input = read_input(&args.input_file)?;
+        let converted = convert_input(&input, args)?;
+        let report = &converted.report;
+        let wasm = &converted.wasm;
 
-    if args.imports {
-        show_module_imports(&module);
-    } else {
-        common::do_module_replacements(&mut module);
-        let wasm = module.emit_wasm();
+        if !args.quiet && args.format == OutputFormat::Text {
+            show_conversion_report(out, report)?;
+        }
 
-        let output_wasm = Path::new(&args.output_file);
-        if is_wat(output_wasm) {
+        let output = if is_wat(output_wasm) {
             // write using wat printer
-            let wat = wasmprinter::print_bytes(&wasm)?;
-            std::fs::write(output_wasm, wat)?;
+            wasmprinter::print_bytes(wasm)
+                .map_err(|e| Wasi2icError::Print(e.to_string()))?
+                .into_bytes()
+        } else if args.gzip || is_gz(output_wasm) {
+            converted.gzipped_wasm()?
         } else {
-            std::fs::write(output_wasm, wasm)?;
+            wasm.clone()
         };
 
-        let imports = get_module_imports(&module);
+        if args.output_file == STDIO {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(&output)?;
+            stdout.flush()?;
+        } else {
+            std::fs::write(output_wasm, output)?;
+        }
+
+        let module_check = ModuleCheck::new(wasm, args)?;
 
-        for (mname, _fname) in imports {
-            if mname != "ic0" {
-                show_module_imports(&module);
-                return Err(anyhow::anyhow!("There are imports remaining that are not compatible with the Internet Computer."));
-            }
+        match args.format {
+            OutputFormat::Text => show_module_check(out, &module_check, args.quiet)?,
+            OutputFormat::Json => writeln!(
+                out,
+                "{:#}",
+                output::conversion_document(
+                    &args.input_file,
+                    &args.output_file,
+                    report,
+                    &module_check
+                )
+            )?,
+        }
+
+        if !module_check.remaining.is_empty() {
+            return Err(Wasi2icError::UnresolvedImports {
+                imports: module_check.remaining,
+            });
+        }
+
+        if module_check.has_ic0_errors() {
+            return Err(Wasi2icError::InvalidIc0Imports {
+                issues: module_check
+                    .ic0_issues
+                    .into_iter()
+                    .filter(Ic0ImportIssue::is_error)
+                    .collect(),
+            });
+        }
+
+        if !module_check.is_within_limits() {
+            return Err(Wasi2icError::LimitsExceeded {
+                violations: module_check.limit_violations,
+            });
         }
     }
 
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    env_logger::init();
-    let args = arguments::Wasm2icArgs::parse();
+/// returns the process exit code reported for the error
+pub fn error_exit_code(error: &Wasi2icError) -> u8 {
+    match error {
+        Wasi2icError::Io(_) => 10,
+        Wasi2icError::Parse(_) => 11,
+        Wasi2icError::InvalidOption(_) => 12,
+        Wasi2icError::MappingFile(_) => 13,
+        Wasi2icError::UnresolvedImports { .. } => 14,
+        Wasi2icError::InvalidIc0Imports { .. } => 15,
+        Wasi2icError::LimitsExceeded { .. } => 16,
+        Wasi2icError::BatchFailed { .. } => 17,
+        Wasi2icError::InconsistentConversion { .. } => 18,
+        Wasi2icError::Optimize(_) => 19,
+        Wasi2icError::CommandModule { .. } => 20,
+        Wasi2icError::Print(_) => 21,
+        Wasi2icError::Mapping { .. } => 22,
+    }
+}
 
-    do_wasm_file_processing(&args)?;
+fn run(args: &mut Wasm2icArgs) -> Result<ExitCode, Wasi2icError> {
+    if args.is_batch() {
+        batch::do_batch_processing(args, &mut std::io::stdout())?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    Ok(())
+    args.resolve_files()?;
+
+    if args.check {
+        let status = do_wasm_file_check(args);
+        return Ok(ExitCode::from(status.exit_code()));
+    }
+
+    do_wasm_file_processing(args)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let mut args = arguments::Wasm2icArgs::parse();
+
+    match run(&mut args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(error_exit_code(&e))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::Wasi2icError;
+
+/// Explicit mapping of the imported functions to the functions replacing them.
+///
+/// The mapping file groups the entries by the import module name, in TOML:
+/// ```toml
+/// [wasi_snapshot_preview1]
+/// clock_time_get = "my_clock"
+///
+/// [env]
+/// some_function = "my_some_function"
+/// ```
+/// or in JSON:
+/// ```json
+/// { "wasi_snapshot_preview1": { "clock_time_get": "my_clock" } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportMapping {
+    entries: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ImportMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the import `module_name::import_name` to the function `target`.
+    pub fn insert(&mut self, module_name: &str, import_name: &str, target: &str) {
+        self.entries
+            .entry(module_name.to_string())
+            .or_default()
+            .insert(import_name.to_string(), target.to_string());
+    }
+
+    /// returns the replacement function name for the import `module_name::import_name`, if it is mapped
+    pub fn get(&self, module_name: &str, import_name: &str) -> Option<&str> {
+        self.entries
+            .get(module_name)
+            .and_then(|names| names.get(import_name))
+            .map(|target| target.as_str())
+    }
+
+    /// Iterate over the mapping entries as triples: (module name, import name, target function name)
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.entries.iter().flat_map(|(module_name, names)| {
+            names.iter().map(move |(import_name, target)| {
+                (module_name.as_str(), import_name.as_str(), target.as_str())
+            })
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.values().all(|names| names.is_empty())
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, Wasi2icError> {
+        Ok(ImportMapping {
+            entries: toml::from_str(text).map_err(|e| Wasi2icError::MappingFile(e.to_string()))?,
+        })
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, Wasi2icError> {
+        Ok(ImportMapping {
+            entries: serde_json::from_str(text)
+                .map_err(|e| Wasi2icError::MappingFile(e.to_string()))?,
+        })
+    }
+
+    /// Read the mapping file, files with the `.json` extension are parsed as JSON, all others as TOML.
+    pub fn from_file(path: &Path) -> Result<Self, Wasi2icError> {
+        let text = std::fs::read_to_string(path)?;
+
+        let is_json = path.extension().is_some_and(|ext| ext == "json");
+
+        let mapping = if is_json {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        };
+
+        mapping.map_err(|e| {
+            Wasi2icError::MappingFile(format!(
+                "Failed to parse mapping file '{}': {e}",
+                path.display()
+            ))
+        })
+    }
+}
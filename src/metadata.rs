@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ic_wasm::metadata::Kind;
+
+use crate::error::Wasi2icError;
+
+/// Name of the metadata section holding the Candid interface of the canister.
+pub const CANDID_SERVICE: &str = "candid:service";
+
+/// Visibility of the metadata section: public sections can be read by anyone, private ones only by the controllers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MetadataVisibility {
+    /// Stored in the `icp:public` section.
+    #[default]
+    Public,
+
+    /// Stored in the `icp:private` section.
+    Private,
+}
+
+impl fmt::Display for MetadataVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataVisibility::Public => write!(f, "public"),
+            MetadataVisibility::Private => write!(f, "private"),
+        }
+    }
+}
+
+impl FromStr for MetadataVisibility {
+    type Err = Wasi2icError;
+
+    /// Parse the visibility: `public` or `private`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(MetadataVisibility::Public),
+            "private" => Ok(MetadataVisibility::Private),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown metadata visibility '{s}', expected one of: public, private"
+            ))),
+        }
+    }
+}
+
+/// Metadata section embedded into the converted module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// Metadata name, e.g. `candid:service`.
+    pub name: String,
+
+    /// Section content.
+    pub data: Vec<u8>,
+
+    /// Visibility of the section.
+    pub visibility: MetadataVisibility,
+}
+
+impl Metadata {
+    /// returns the name of the custom section storing the metadata, e.g. `icp:public candid:service`
+    pub fn section_name(&self) -> String {
+        format!("icp:{} {}", self.visibility, self.name)
+    }
+}
+
+impl FromStr for Metadata {
+    type Err = Wasi2icError;
+
+    /// Parse the metadata given as `NAME=VALUE`, public by default, or prefixed with the visibility,
+    /// e.g. `private:NAME=VALUE`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (visibility, s) = match s.split_once(':') {
+            Some((visibility @ ("public" | "private"), rest)) => (visibility.parse()?, rest),
+            _ => (MetadataVisibility::Public, s),
+        };
+
+        let (name, value) = s.split_once('=').ok_or_else(|| {
+            Wasi2icError::InvalidOption(format!(
+                "Expected [public:|private:]NAME=VALUE, found '{s}'"
+            ))
+        })?;
+
+        if name.is_empty() {
+            return Err(Wasi2icError::InvalidOption(format!(
+                "Empty metadata name in '{s}'"
+            )));
+        }
+
+        Ok(Metadata {
+            name: name.to_string(),
+            data: value.as_bytes().to_vec(),
+            visibility,
+        })
+    }
+}
+
+/// Embed the metadata sections into the module, replacing the sections of the same name of either visibility.
+///
+/// returns the names and sizes in bytes of the sections in the module at the end, an entry replaced
+/// by a later entry of the same name is not listed
+pub(crate) fn add_metadata(
+    module: &mut walrus::Module,
+    metadata: &[Metadata],
+) -> Vec<(String, u64)> {
+    let mut added: Vec<(&str, String, u64)> = Vec::new();
+
+    for metadata in metadata {
+        let kind = match metadata.visibility {
+            MetadataVisibility::Public => Kind::Public,
+            MetadataVisibility::Private => Kind::Private,
+        };
+
+        ic_wasm::metadata::remove_metadata(module, &metadata.name);
+        ic_wasm::metadata::add_metadata(module, kind, &metadata.name, metadata.data.clone());
+
+        added.retain(|(name, _, _)| *name != metadata.name);
+        added.push((
+            &metadata.name,
+            metadata.section_name(),
+            metadata.data.len() as u64,
+        ));
+    }
+
+    added
+        .into_iter()
+        .map(|(_, section_name, size)| (section_name, size))
+        .collect()
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ic_wasm::optimize::OptLevel;
+
+use crate::common::emit_wasm_keeping_customs;
+use crate::error::Wasi2icError;
+use crate::report::OptimizeReport;
+
+/// Post-processing stage run on the converted module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizeLevel {
+    /// Remove the custom sections other than the `icp:` metadata and the unused functions, like `ic-wasm shrink`,
+    /// but the `name` section is kept, use `--strip=names` to remove it.
+    Shrink,
+
+    /// Shrink, then run `wasm-opt -O0`, same as `ic-wasm optimize`.
+    O0,
+    /// Shrink, then run `wasm-opt -O1`.
+    O1,
+    /// Shrink, then run `wasm-opt -O2`.
+    O2,
+    /// Shrink, then run `wasm-opt -O3`.
+    O3,
+    /// Shrink, then run `wasm-opt -O4`.
+    O4,
+    /// Shrink, then run `wasm-opt -Os` optimizing for size.
+    Os,
+    /// Shrink, then run `wasm-opt -Oz` optimizing aggressively for size.
+    Oz,
+}
+
+impl fmt::Display for OptimizeLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptimizeLevel::Shrink => write!(f, "shrink"),
+            OptimizeLevel::O0 => write!(f, "O0"),
+            OptimizeLevel::O1 => write!(f, "O1"),
+            OptimizeLevel::O2 => write!(f, "O2"),
+            OptimizeLevel::O3 => write!(f, "O3"),
+            OptimizeLevel::O4 => write!(f, "O4"),
+            OptimizeLevel::Os => write!(f, "Os"),
+            OptimizeLevel::Oz => write!(f, "Oz"),
+        }
+    }
+}
+
+impl FromStr for OptimizeLevel {
+    type Err = Wasi2icError;
+
+    /// Parse the level: `shrink`, `O0`, `O1`, `O2`, `O3`, `O4`, `Os` or `Oz`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shrink" => Ok(OptimizeLevel::Shrink),
+            "O0" => Ok(OptimizeLevel::O0),
+            "O1" => Ok(OptimizeLevel::O1),
+            "O2" => Ok(OptimizeLevel::O2),
+            "O3" => Ok(OptimizeLevel::O3),
+            "O4" => Ok(OptimizeLevel::O4),
+            "Os" => Ok(OptimizeLevel::Os),
+            "Oz" => Ok(OptimizeLevel::Oz),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown optimization level '{s}', expected one of: shrink, O0, O1, O2, O3, O4, Os, Oz"
+            ))),
+        }
+    }
+}
+
+impl OptimizeLevel {
+    /// returns the `wasm-opt` level, None if only shrinking is done
+    fn opt_level(&self) -> Option<OptLevel> {
+        match self {
+            OptimizeLevel::Shrink => None,
+            OptimizeLevel::O0 => Some(OptLevel::O0),
+            OptimizeLevel::O1 => Some(OptLevel::O1),
+            OptimizeLevel::O2 => Some(OptLevel::O2),
+            OptimizeLevel::O3 => Some(OptLevel::O3),
+            OptimizeLevel::O4 => Some(OptLevel::O4),
+            OptimizeLevel::Os => Some(OptLevel::Os),
+            OptimizeLevel::Oz => Some(OptLevel::Oz),
+        }
+    }
+}
+
+/// Shrink and optimize the module, the `icp:` metadata sections and the `name` section are kept.
+///
+/// The module size before the optimization is measured by the caller, the module is emitted once to measure the size after.
+///
+/// returns the module size before and after the optimization, fails if the optimizer fails
+pub(crate) fn optimize_module(
+    module: &mut walrus::Module,
+    level: OptimizeLevel,
+    size_before: u64,
+) -> Result<OptimizeReport, Wasi2icError> {
+    ic_wasm::shrink::shrink(module);
+
+    if let Some(opt_level) = level.opt_level() {
+        // the optimizer re-parses the module, the metadata is re-added so that it cannot get lost
+        let metadata: Vec<walrus::RawCustomSection> = module
+            .customs
+            .iter()
+            .map(|(_, section)| walrus::RawCustomSection {
+                name: section.name().to_string(),
+                data: section.data(&walrus::IdsToIndices::default()).into_owned(),
+            })
+            .collect();
+
+        ic_wasm::optimize::optimize(module, &opt_level, false, &None, true)
+            .map_err(|e| Wasi2icError::Optimize(e.to_string()))?;
+
+        for section in metadata {
+            module.customs.remove_raw(&section.name);
+            module.customs.add(section);
+        }
+    }
+
+    let size_after = emit_wasm_keeping_customs(module).len() as u64;
+
+    Ok(OptimizeReport {
+        level: level.to_string(),
+        size_before,
+        size_after,
+    })
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::adapters::AdapterRule;
+use crate::common::{WASI_SNAPSHOT_PREVIEW1, WASI_UNSTABLE};
+use crate::error::Wasi2icError;
+use crate::init::{CommandMode, InitMode, StartOrder, INITIALIZE};
+use crate::limits::LimitsProfile;
+use crate::mapping::ImportMapping;
+use crate::metadata::Metadata;
+use crate::optimize::OptimizeLevel;
+use crate::resolver::{PrefixResolver, ReplacementResolver};
+use crate::strip::StripSection;
+
+/// Kind of the stub function generated for the imports left without replacement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StubMode {
+    /// The stub traps with the `unreachable` instruction.
+    Unreachable,
+
+    /// The stub calls `ic0::trap` with the message naming the unsupported function.
+    Trap,
+
+    /// The stub returns the given errno value, only applies to the functions returning a single i32 value.
+    /// Other functions get the trapping stub instead.
+    Errno(i32),
+}
+
+/// WASI errno value: function not supported.
+pub const ENOSYS: i32 = 52;
+
+impl fmt::Display for StubMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StubMode::Unreachable => write!(f, "unreachable"),
+            StubMode::Trap => write!(f, "trap"),
+            StubMode::Errno(errno) => write!(f, "errno:{errno}"),
+        }
+    }
+}
+
+impl FromStr for StubMode {
+    type Err = Wasi2icError;
+
+    /// Parse the stub mode: `unreachable`, `trap`, `errno` (returns ENOSYS) or `errno:<value>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unreachable" => Ok(StubMode::Unreachable),
+            "trap" => Ok(StubMode::Trap),
+            "errno" => Ok(StubMode::Errno(ENOSYS)),
+            _ => match s.strip_prefix("errno:") {
+                Some(errno) => Ok(StubMode::Errno(errno.parse().map_err(|_| {
+                    Wasi2icError::InvalidOption(format!("Invalid errno value '{errno}'"))
+                })?)),
+                None => Err(Wasi2icError::InvalidOption(format!(
+                    "Unknown stub mode '{s}', expected one of: unreachable, trap, errno, errno:<value>"
+                ))),
+            },
+        }
+    }
+}
+
+/// Module conversion options.
+#[derive(Clone)]
+pub struct Options {
+    /// Decides on the names of the replacement functions.
+    pub resolver: Arc<dyn ReplacementResolver>,
+
+    /// Explicit import mapping, takes priority over the replacement function names given by the resolver.
+    pub mapping: ImportMapping,
+
+    /// Import modules whose functions are rewired to their replacements.
+    pub modules: Vec<String>,
+
+    /// Generate stubs for the imports left without replacement, stubs are not generated if not set.
+    pub stubs: Option<StubMode>,
+
+    /// Stub modes for the individual imports, these take priority over `stubs`. The imports are given as
+    /// `module::name`, a bare function name is a shorthand for the WASI modules.
+    pub import_stubs: BTreeMap<String, StubMode>,
+
+    /// Validate the WASI imports and their replacements against the signatures given by the WASI specification.
+    pub validate_wasi_signatures: bool,
+
+    /// Rules for generating adapters between the imports and the replacements of a different type,
+    /// adapters are not generated if empty.
+    pub adapters: Vec<AdapterRule>,
+
+    /// Convert the module again even if it was already converted by wasi2ic,
+    /// otherwise the module is left unchanged.
+    pub reconvert: bool,
+
+    /// Keep the DWARF debug sections of the module given as bytes and update their code addresses,
+    /// otherwise the debug sections are dropped.
+    pub keep_dwarf: bool,
+
+    /// Section groups removed from the converted module, nothing is removed if empty.
+    pub strip: Vec<StripSection>,
+
+    /// Metadata sections embedded into the converted module.
+    pub metadata: Vec<Metadata>,
+
+    /// Shrink and optimize the converted module, no optimization is done if not set.
+    pub optimize: Option<OptimizeLevel>,
+
+    /// Way the initialization function is called: as the module start function or from the canister lifecycle exports.
+    pub init_mode: InitMode,
+
+    /// Order of `_initialize` and the original start function in the generated start function,
+    /// used when the module already has a start function.
+    pub start_order: StartOrder,
+
+    /// Names of the initializer function candidates in the priority order, the first one found is used.
+    pub initializers: Vec<String>,
+
+    /// Handling of the WASI commands exporting `_start`, the commands are converted like other modules if not set.
+    pub command: Option<CommandMode>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            resolver: Arc::new(PrefixResolver::default()),
+            mapping: ImportMapping::default(),
+            modules: vec![
+                WASI_UNSTABLE.to_string(),
+                WASI_SNAPSHOT_PREVIEW1.to_string(),
+            ],
+            stubs: None,
+            import_stubs: BTreeMap::new(),
+            validate_wasi_signatures: true,
+            adapters: Vec::new(),
+            reconvert: false,
+            keep_dwarf: false,
+            strip: Vec::new(),
+            metadata: Vec::new(),
+            optimize: None,
+            init_mode: InitMode::Start,
+            start_order: StartOrder::InitializeFirst,
+            initializers: vec![INITIALIZE.to_string()],
+            command: None,
+        }
+    }
+}
+
+impl Options {
+    /// returns the stub mode to use for the unresolved import, or None if no stub should be generated
+    pub fn stub_mode(&self, module_name: &str, import_name: &str) -> Option<StubMode> {
+        let is_wasi = module_name == WASI_UNSTABLE || module_name == WASI_SNAPSHOT_PREVIEW1;
+
+        self.import_stubs
+            .get(&format!("{module_name}::{import_name}"))
+            .or_else(|| {
+                is_wasi
+                    .then(|| self.import_stubs.get(import_name))
+                    .flatten()
+            })
+            .copied()
+            .or(self.stubs)
+    }
+}
+
+/// Options of the [`check_bytes`](crate::check_bytes) module check.
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    /// Do not check the `ic0` imports against the System API specification.
+    pub skip_ic0_check: bool,
+
+    /// Report the `ic0` functions unknown to the tool as errors.
+    pub strict_ic0: bool,
+
+    /// Limits to check the module against, the limits are not checked if not set.
+    pub limits_profile: Option<LimitsProfile>,
+}
@@ -0,0 +1,235 @@
+use serde_json::{json, Value};
+
+use wasi2ic::{
+    ConversionInfo, ConversionReport, Ic0ImportIssue, ImportResolution, LimitViolation, Signature,
+};
+
+use crate::batch::BatchResult;
+use crate::ModuleCheck;
+
+/// Version of the JSON output schema, increased on incompatible changes.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+fn imports_json(imports: &[(String, String)]) -> Value {
+    imports
+        .iter()
+        .map(|(module, name)| json!({ "module": module, "name": name }))
+        .collect()
+}
+
+fn signature_json(signature: &Signature) -> Value {
+    json!({ "params": signature.params, "results": signature.results })
+}
+
+fn resolution_json(resolution: &ImportResolution) -> Value {
+    let kind = match resolution {
+        ImportResolution::Skipped => "skipped",
+        ImportResolution::ReplacedByName { .. } => "replaced_by_name",
+        ImportResolution::ReplacedByExport { .. } => "replaced_by_export",
+        ImportResolution::ReplacedByMapping { .. } => "replaced_by_mapping",
+        ImportResolution::ReplacedByAdapter { .. } => "replaced_by_adapter",
+        ImportResolution::Stubbed { .. } => "stubbed",
+        ImportResolution::Missing { .. } => "missing",
+        ImportResolution::TypeMismatch { .. } => "type_mismatch",
+        ImportResolution::ReplacementIsImport { .. } => "replacement_is_import",
+        ImportResolution::WasiSignatureMismatch { .. } => "wasi_signature_mismatch",
+    };
+
+    let mut value = json!({
+        "kind": kind,
+        "replaced": resolution.is_replaced(),
+        "replacement": resolution.replacement(),
+        "message": resolution.to_string(),
+    });
+
+    match resolution {
+        ImportResolution::Stubbed { mode } => {
+            value["mode"] = json!(mode.to_string());
+        }
+        ImportResolution::TypeMismatch {
+            expected, actual, ..
+        } => {
+            value["expected"] = signature_json(expected);
+            value["actual"] = signature_json(actual);
+        }
+        ImportResolution::WasiSignatureMismatch {
+            function,
+            expected,
+            actual,
+        } => {
+            value["function"] = json!(function);
+            value["expected"] = signature_json(expected);
+            value["actual"] = signature_json(actual);
+        }
+        _ => {}
+    }
+
+    value
+}
+
+fn ic0_issue_json(issue: &Ic0ImportIssue) -> Value {
+    let mut value = json!({
+        "name": issue.name(),
+        "error": issue.is_error(),
+        "message": issue.to_string(),
+    });
+
+    match issue {
+        Ic0ImportIssue::Unknown { .. } => {
+            value["kind"] = json!("unknown");
+        }
+        Ic0ImportIssue::TypeMismatch {
+            expected, actual, ..
+        } => {
+            value["kind"] = json!("type_mismatch");
+            value["expected"] = signature_json(expected);
+            value["actual"] = signature_json(actual);
+        }
+        Ic0ImportIssue::Deprecated { replacement, .. } => {
+            value["kind"] = json!("deprecated");
+            value["replacement"] = json!(replacement);
+        }
+    }
+
+    value
+}
+
+fn limit_violation_json(violation: &LimitViolation) -> Value {
+    json!({
+        "limit": violation.limit,
+        "measured": violation.measured,
+        "maximum": violation.maximum,
+        "message": violation.to_string(),
+    })
+}
+
+fn limits_profile_json(module_check: &ModuleCheck) -> Value {
+    match module_check.limits_profile {
+        Some((name, version)) => json!({ "name": name, "version": version }),
+        None => Value::Null,
+    }
+}
+
+fn conversion_json(report: &ConversionReport) -> Value {
+    json!({
+        "modified": report.modified,
+        "previous_conversion": report.previous_conversion.as_ref().map(ConversionInfo::to_json),
+        "stripped": report.stripped.as_ref().map(|stripped| json!({
+            "sections": stripped.sections.iter()
+                .map(|(name, size)| json!({ "name": name, "size": size }))
+                .collect::<Vec<_>>(),
+            "bytes_saved": stripped.bytes_saved,
+        })),
+        "optimized": report.optimized.as_ref().map(|optimized| json!({
+            "level": optimized.level,
+            "size_before": optimized.size_before,
+            "size_after": optimized.size_after,
+        })),
+        "metadata": report.metadata.iter()
+            .map(|(name, size)| json!({ "name": name, "size": size }))
+            .collect::<Vec<_>>(),
+        "start_function_set": report.start_function_set,
+        "initializer": report.initializer,
+        "chained_start": report.chained_start.as_ref().map(|chained| json!({
+            "original": chained.original,
+            "order": chained.order.to_string(),
+        })),
+        "init_hooks": report.init_hooks.iter()
+            .map(|hook| json!({ "export": hook.export, "synthesized": hook.synthesized }))
+            .collect::<Vec<_>>(),
+        "removed_exports": report.removed_exports,
+        "imports": report.imports.iter().map(|import| json!({
+            "module": import.module,
+            "name": import.name,
+            "resolution": resolution_json(&import.resolution),
+        })).collect::<Vec<_>>(),
+        "stubs": report.stubs.iter().map(|stub| json!({
+            "module": stub.module,
+            "name": stub.name,
+            "mode": stub.mode.to_string(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// JSON document for the `--imports` listing.
+pub fn imports_document(input_file: &str, imports: &[(String, String)]) -> Value {
+    json!({
+        "version": JSON_SCHEMA_VERSION,
+        "input_file": input_file,
+        "imports": imports_json(imports),
+    })
+}
+
+/// JSON document describing the conversion result.
+///
+/// `status` is `"ok"`, `"unresolved_imports"` if there are imports incompatible with the Internet Computer
+/// remaining after the conversion, `"invalid_ic0_imports"` if the ic0 imports do not match the System API,
+/// or `"limits_exceeded"` if the module exceeds the Internet Computer limits.
+pub fn conversion_document(
+    input_file: &str,
+    output_file: &str,
+    report: &ConversionReport,
+    module_check: &ModuleCheck,
+) -> Value {
+    let status = if !module_check.remaining.is_empty() {
+        "unresolved_imports"
+    } else if module_check.has_ic0_errors() {
+        "invalid_ic0_imports"
+    } else if !module_check.is_within_limits() {
+        "limits_exceeded"
+    } else {
+        "ok"
+    };
+
+    json!({
+        "version": JSON_SCHEMA_VERSION,
+        "input_file": input_file,
+        "output_file": output_file,
+        "status": status,
+        "conversion": conversion_json(report),
+        "remaining_imports": imports_json(&module_check.remaining),
+        "ic0_issues": module_check.ic0_issues.iter().map(ic0_issue_json).collect::<Vec<_>>(),
+        "limits_profile": limits_profile_json(module_check),
+        "limit_violations": module_check.limit_violations.iter().map(limit_violation_json).collect::<Vec<_>>(),
+    })
+}
+
+/// JSON document describing the check mode result.
+pub fn check_document(
+    input_file: &str,
+    status: &str,
+    exit_code: u8,
+    report: Option<&ConversionReport>,
+    module_check: Option<&ModuleCheck>,
+    error: Option<String>,
+) -> Value {
+    let empty_check = ModuleCheck::default();
+    let module_check = module_check.unwrap_or(&empty_check);
+
+    json!({
+        "version": JSON_SCHEMA_VERSION,
+        "input_file": input_file,
+        "status": status,
+        "exit_code": exit_code,
+        "conversion": report.map(conversion_json),
+        "remaining_imports": imports_json(&module_check.remaining),
+        "ic0_issues": module_check.ic0_issues.iter().map(ic0_issue_json).collect::<Vec<_>>(),
+        "limits_profile": limits_profile_json(module_check),
+        "limit_violations": module_check.limit_violations.iter().map(limit_violation_json).collect::<Vec<_>>(),
+        "error": error,
+    })
+}
+
+/// JSON document summarizing the batch conversion, containing the conversion documents of the modules.
+pub fn batch_document(results: &[BatchResult]) -> Value {
+    json!({
+        "version": JSON_SCHEMA_VERSION,
+        "modules": results.iter().map(|result| json!({
+            "input_file": result.input_file,
+            "output_file": result.output_file,
+            "status": if result.error.is_some() { "failed" } else { "ok" },
+            "error": result.error,
+            "document": result.document,
+        })).collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,103 @@
+use crate::compression;
+use crate::error::Wasi2icError;
+
+/// Magic bytes starting a binary Wasm module.
+pub(crate) const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Decode the module given as binary Wasm, Wasm text or either of them compressed with gzip.
+///
+/// The format is sniffed from the magic bytes, anything other than a binary module is parsed as text.
+///
+/// returns the module in the binary Wasm format
+pub(crate) fn decode_module(bytes: &[u8]) -> Result<Vec<u8>, Wasi2icError> {
+    let decompressed;
+    let bytes = if compression::is_gzip(bytes) {
+        decompressed = compression::gunzip(bytes)?;
+        &decompressed
+    } else {
+        bytes
+    };
+
+    if bytes.starts_with(WASM_MAGIC) {
+        Ok(bytes.to_vec())
+    } else {
+        wat::parse_bytes(bytes)
+            .map(|wasm| wasm.into_owned())
+            .map_err(|e| Wasi2icError::Parse(e.to_string()))
+    }
+}
+
+/// returns the versions of the line programs found in the `.debug_line` section of the module
+fn debug_line_versions(wasm: &[u8]) -> Result<Vec<u16>, Wasi2icError> {
+    let mut versions = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        let payload = payload.map_err(|e| Wasi2icError::Parse(e.to_string()))?;
+
+        let wasmparser::Payload::CustomSection(reader) = payload else {
+            continue;
+        };
+
+        if reader.name() != ".debug_line" {
+            continue;
+        }
+
+        // each line program starts with the unit length (32-bit, or 64-bit after the 0xffffffff escape)
+        // followed by the 16-bit version
+        let mut data = reader.data();
+        while data.len() >= 6 {
+            let (length, header_size) =
+                match u32::from_le_bytes([data[0], data[1], data[2], data[3]]) {
+                    0xffff_ffff if data.len() >= 14 => {
+                        let mut length = [0u8; 8];
+                        length.copy_from_slice(&data[4..12]);
+                        (u64::from_le_bytes(length), 12)
+                    }
+                    length => (length as u64, 4),
+                };
+
+            versions.push(u16::from_le_bytes([
+                data[header_size],
+                data[header_size + 1],
+            ]));
+
+            let next = usize::try_from(header_size as u64 + length).unwrap_or(usize::MAX);
+            data = data.get(next..).unwrap_or_default();
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Parse the module given in the binary Wasm format.
+///
+/// With `keep_dwarf` the `.debug_*` sections are kept and their code addresses are rewritten when the module
+/// is emitted, otherwise the debug sections are dropped.
+pub(crate) fn parse_wasm(wasm: &[u8], keep_dwarf: bool) -> Result<walrus::Module, Wasi2icError> {
+    if keep_dwarf {
+        // walrus cannot convert the DWARF 5 line rows referring to the primary source file (file index 0)
+        if debug_line_versions(wasm)?
+            .iter()
+            .any(|version| *version >= 5)
+        {
+            return Err(Wasi2icError::Parse(
+                "DWARF 5 line tables cannot be kept, build the module with DWARF 4 (e.g. -gdwarf-4)"
+                    .to_string(),
+            ));
+        }
+
+        // same configuration as the dfx parser, additionally tracking the code offsets for DWARF
+        let mut config = walrus::ModuleConfig::new();
+        config
+            .generate_name_section(true)
+            .generate_producers_section(false)
+            .generate_dwarf(true);
+
+        return config
+            .parse(wasm)
+            .map_err(|e| Wasi2icError::Parse(e.to_string()));
+    }
+
+    // use the same parser as dfx here
+    ic_wasm::utils::parse_wasm(wasm, true).map_err(|e| Wasi2icError::Parse(e.to_string()))
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use crate::info::ConversionInfo;
+use crate::init::StartOrder;
+use crate::limits::LimitViolation;
+use crate::options::StubMode;
+
+/// Function signature in a printable form, e.g. `(i32, i32) -> (i32)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub params: Vec<String>,
+    pub results: Vec<String>,
+}
+
+impl Signature {
+    pub(crate) fn from_type(module: &walrus::Module, ty: walrus::TypeId) -> Signature {
+        let ty = module.types.get(ty);
+
+        Signature {
+            params: ty.params().iter().map(|t| t.to_string()).collect(),
+            results: ty.results().iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}) -> ({})",
+            self.params.join(", "),
+            self.results.join(", ")
+        )
+    }
+}
+
+/// Outcome of resolving a single function import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportResolution {
+    /// The import module is not subject to rewiring, the import is left untouched.
+    Skipped,
+
+    /// Replaced by the function with the expected name.
+    ReplacedByName { replacement: String },
+
+    /// Replaced by the function exported under the expected name.
+    ReplacedByExport { replacement: String },
+
+    /// Replaced by the function given in the import mapping.
+    ReplacedByMapping { replacement: String },
+
+    /// Replaced by the generated adapter calling the replacement function of a different type.
+    ReplacedByAdapter { replacement: String },
+
+    /// Replaced by the generated stub, as no usable replacement function was found.
+    Stubbed { mode: StubMode },
+
+    /// No replacement function was found.
+    Missing { replacement: String },
+
+    /// The replacement function was found, but its type differs from the import type.
+    TypeMismatch {
+        replacement: String,
+        expected: Signature,
+        actual: Signature,
+    },
+
+    /// The replacement function found is itself an imported function.
+    ReplacementIsImport { replacement: String },
+
+    /// The import or its replacement (named by `function`) does not have the signature given by the WASI specification.
+    WasiSignatureMismatch {
+        function: String,
+        expected: Signature,
+        actual: Signature,
+    },
+}
+
+impl ImportResolution {
+    /// returns true if the import was rewired to a replacement function, an adapter or a stub
+    pub fn is_replaced(&self) -> bool {
+        matches!(
+            self,
+            ImportResolution::ReplacedByName { .. }
+                | ImportResolution::ReplacedByExport { .. }
+                | ImportResolution::ReplacedByMapping { .. }
+                | ImportResolution::ReplacedByAdapter { .. }
+                | ImportResolution::Stubbed { .. }
+        )
+    }
+
+    /// returns the name of the replacement function, if one was searched for
+    pub fn replacement(&self) -> Option<&str> {
+        match self {
+            ImportResolution::Skipped
+            | ImportResolution::Stubbed { .. }
+            | ImportResolution::WasiSignatureMismatch { .. } => None,
+            ImportResolution::ReplacedByName { replacement }
+            | ImportResolution::ReplacedByExport { replacement }
+            | ImportResolution::ReplacedByMapping { replacement }
+            | ImportResolution::ReplacedByAdapter { replacement }
+            | ImportResolution::Missing { replacement }
+            | ImportResolution::TypeMismatch { replacement, .. }
+            | ImportResolution::ReplacementIsImport { replacement } => Some(replacement),
+        }
+    }
+}
+
+impl fmt::Display for ImportResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportResolution::Skipped => write!(f, "skipped"),
+            ImportResolution::ReplacedByName { replacement } => {
+                write!(f, "replaced by '{replacement}'")
+            }
+            ImportResolution::ReplacedByExport { replacement } => {
+                write!(f, "replaced by export '{replacement}'")
+            }
+            ImportResolution::ReplacedByMapping { replacement } => {
+                write!(f, "replaced by mapped '{replacement}'")
+            }
+            ImportResolution::ReplacedByAdapter { replacement } => {
+                write!(f, "replaced by adapter calling '{replacement}'")
+            }
+            ImportResolution::Stubbed { mode } => write!(f, "replaced by {mode} stub"),
+            ImportResolution::Missing { replacement } => {
+                write!(f, "missing replacement '{replacement}'")
+            }
+            ImportResolution::TypeMismatch {
+                replacement,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "type mismatch for '{replacement}': expected {expected}, found {actual}"
+            ),
+            ImportResolution::ReplacementIsImport { replacement } => {
+                write!(f, "replacement '{replacement}' is an imported function")
+            }
+            ImportResolution::WasiSignatureMismatch {
+                function,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "'{function}' does not match the WASI signature: expected {expected}, found {actual}"
+            ),
+        }
+    }
+}
+
+/// Problem found with an `ic0` import when checking it against the System API specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ic0ImportIssue {
+    /// The function is not part of the System API known to the tool, this is only an error in the strict mode,
+    /// as the function may have been added to the specification later.
+    Unknown { name: String, strict: bool },
+
+    /// The import type differs from the System API signature.
+    TypeMismatch {
+        name: String,
+        expected: Signature,
+        actual: Signature,
+    },
+
+    /// The function is deprecated in favour of the `replacement` function.
+    Deprecated { name: String, replacement: String },
+}
+
+impl Ic0ImportIssue {
+    /// returns the name of the imported function
+    pub fn name(&self) -> &str {
+        match self {
+            Ic0ImportIssue::Unknown { name, .. }
+            | Ic0ImportIssue::TypeMismatch { name, .. }
+            | Ic0ImportIssue::Deprecated { name, .. } => name,
+        }
+    }
+
+    /// returns true if the Internet Computer rejects the module with this import
+    pub fn is_error(&self) -> bool {
+        match self {
+            Ic0ImportIssue::Unknown { strict, .. } => *strict,
+            Ic0ImportIssue::TypeMismatch { .. } => true,
+            Ic0ImportIssue::Deprecated { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Ic0ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ic0ImportIssue::Unknown { name, .. } => {
+                write!(f, "'{name}' is not a System API function")
+            }
+            Ic0ImportIssue::TypeMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "type mismatch for '{name}': expected {expected}, found {actual}"
+            ),
+            Ic0ImportIssue::Deprecated { name, replacement } => {
+                write!(f, "'{name}' is deprecated, use '{replacement}' instead")
+            }
+        }
+    }
+}
+
+/// Resolution outcome of a function import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    pub module: String,
+    pub name: String,
+    pub resolution: ImportResolution,
+}
+
+/// Stub function generated for an import left without replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StubReport {
+    pub module: String,
+    pub name: String,
+    pub mode: StubMode,
+}
+
+/// Canister lifecycle export calling the initialization function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitHookReport {
+    /// Export name, `canister_init` or `canister_post_upgrade`.
+    pub export: String,
+
+    /// true if the export was generated, false if the existing export was wrapped
+    pub synthesized: bool,
+}
+
+/// Original start function combined with the initialization function into a new start function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainedStartReport {
+    /// Name of the original start function.
+    pub original: String,
+
+    /// Order of the calls in the new start function.
+    pub order: StartOrder,
+}
+
+/// Custom sections removed from the module by the strip options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripReport {
+    /// Names and sizes in bytes of the custom sections removed.
+    pub sections: Vec<(String, u64)>,
+
+    /// Decrease of the module size in bytes.
+    pub bytes_saved: u64,
+}
+
+/// Module size change done by the optimization stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptimizeReport {
+    /// Optimization level used, e.g. `shrink` or `Oz`.
+    pub level: String,
+
+    /// Module size in bytes before the optimization.
+    pub size_before: u64,
+
+    /// Module size in bytes after the optimization.
+    pub size_after: u64,
+}
+
+/// Summary of the changes done to a module during the conversion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionReport {
+    /// All function imports found in the original module together with their resolution.
+    pub imports: Vec<ImportReport>,
+
+    /// true if the module start function was set to the initialization function
+    pub start_function_set: bool,
+
+    /// Name of the initializer function called during the initialization, None if no initializer was found.
+    pub initializer: Option<String>,
+
+    /// Original start function chained with the initialization function, None if there was no start function.
+    pub chained_start: Option<ChainedStartReport>,
+
+    /// Lifecycle exports calling the initialization function, only set in the `hooks` init mode.
+    pub init_hooks: Vec<InitHookReport>,
+
+    /// Names of the exports removed from the module.
+    pub removed_exports: Vec<String>,
+
+    /// Stubs generated for the imports left without replacement.
+    pub stubs: Vec<StubReport>,
+
+    /// true if the module was modified
+    pub modified: bool,
+
+    /// Record of the earlier conversion found in the module, None if the module was not converted before.
+    pub previous_conversion: Option<ConversionInfo>,
+
+    /// Sections removed from the module, None if stripping was not requested.
+    pub stripped: Option<StripReport>,
+
+    /// Names and sizes in bytes of the metadata sections embedded into the module.
+    pub metadata: Vec<(String, u64)>,
+
+    /// Module size change done by the optimization, None if the optimization was not requested.
+    pub optimized: Option<OptimizeReport>,
+}
+
+impl ConversionReport {
+    /// Imports that were rewired to their replacement functions, adapters or stubs.
+    pub fn replaced(&self) -> impl Iterator<Item = &ImportReport> {
+        self.imports.iter().filter(|i| i.resolution.is_replaced())
+    }
+
+    /// Imports that were subject to rewiring, but could not be replaced.
+    pub fn unresolved(&self) -> impl Iterator<Item = &ImportReport> {
+        self.imports
+            .iter()
+            .filter(|i| !i.resolution.is_replaced() && i.resolution != ImportResolution::Skipped)
+    }
+}
+
+/// Result of checking a module for the compatibility with the Internet Computer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// All function imports of the module as pairs: (module name, function name).
+    pub imports: Vec<(String, String)>,
+
+    /// Issues found with the `ic0` imports, empty if the check was skipped.
+    pub ic0_issues: Vec<Ic0ImportIssue>,
+
+    /// Limits exceeded by the module, empty if the limits were not checked.
+    pub limit_violations: Vec<LimitViolation>,
+}
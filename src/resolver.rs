@@ -0,0 +1,49 @@
+/// Default prefix of the replacement functions provided by the ic-wasi-polyfill library.
+pub const DEFAULT_PREFIX: &str = "__ic_custom_";
+
+/// Default template for the replacement function names.
+pub const DEFAULT_TEMPLATE: &str = "{prefix}{name}";
+
+/// Decides on the function name that replaces an imported function.
+pub trait ReplacementResolver: Send + Sync {
+    /// returns the name of the function replacing the import `module_name::import_name`
+    fn replacement_name(&self, module_name: &str, import_name: &str) -> String;
+}
+
+/// Resolver building replacement names from a prefix and a name template.
+///
+/// The template supports the following placeholders:
+/// `{prefix}` - the configured prefix, `{module}` - import module name, `{name}` - import function name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixResolver {
+    pub prefix: String,
+    pub template: String,
+}
+
+impl PrefixResolver {
+    pub fn new(prefix: &str) -> Self {
+        Self::with_template(prefix, DEFAULT_TEMPLATE)
+    }
+
+    pub fn with_template(prefix: &str, template: &str) -> Self {
+        PrefixResolver {
+            prefix: prefix.to_string(),
+            template: template.to_string(),
+        }
+    }
+}
+
+impl Default for PrefixResolver {
+    fn default() -> Self {
+        PrefixResolver::new(DEFAULT_PREFIX)
+    }
+}
+
+impl ReplacementResolver for PrefixResolver {
+    fn replacement_name(&self, module_name: &str, import_name: &str) -> String {
+        self.template
+            .replace("{prefix}", &self.prefix)
+            .replace("{module}", module_name)
+            .replace("{name}", import_name)
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+use std::str::FromStr;
+
+use wasmparser::{Parser, Payload};
+
+use crate::error::Wasi2icError;
+use crate::info::INFO_SECTION_NAME;
+use crate::report::StripReport;
+
+/// Group of sections removed from the converted module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StripSection {
+    /// The `name` section with the module, function and local names.
+    Names,
+
+    /// The DWARF `.debug_*` sections.
+    Dwarf,
+
+    /// The `producers` section.
+    Producers,
+
+    /// The `target_features` section.
+    TargetFeatures,
+
+    /// All of the above and any other custom section, except the `icp:` metadata and the `wasi2ic:info` record.
+    All,
+}
+
+impl fmt::Display for StripSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StripSection::Names => write!(f, "names"),
+            StripSection::Dwarf => write!(f, "dwarf"),
+            StripSection::Producers => write!(f, "producers"),
+            StripSection::TargetFeatures => write!(f, "target-features"),
+            StripSection::All => write!(f, "all"),
+        }
+    }
+}
+
+impl FromStr for StripSection {
+    type Err = Wasi2icError;
+
+    /// Parse the section group: `names`, `dwarf`, `producers`, `target-features` or `all`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "names" => Ok(StripSection::Names),
+            "dwarf" => Ok(StripSection::Dwarf),
+            "producers" => Ok(StripSection::Producers),
+            "target-features" | "target_features" => Ok(StripSection::TargetFeatures),
+            "all" => Ok(StripSection::All),
+            _ => Err(Wasi2icError::InvalidOption(format!(
+                "Unknown section group '{s}', expected one of: names, dwarf, producers, target-features, all"
+            ))),
+        }
+    }
+}
+
+/// returns true if the section group is removed by the strip options given
+pub(crate) fn strips(strip: &[StripSection], section: StripSection) -> bool {
+    strip
+        .iter()
+        .any(|s| *s == section || *s == StripSection::All)
+}
+
+/// Custom section found in the module binary.
+pub(crate) struct CustomSection {
+    /// Section name.
+    pub name: String,
+
+    /// Size in bytes of the section name and data.
+    pub size: u64,
+}
+
+impl CustomSection {
+    /// returns the size in bytes of the whole section in the module binary, with the section id and sizes
+    pub fn encoded_size(&self) -> u64 {
+        encoded_section_size(&self.name, self.size - self.name.len() as u64)
+    }
+}
+
+/// returns the size in bytes of a custom section with the given name and data length in the module binary
+pub(crate) fn encoded_section_size(name: &str, data_len: u64) -> u64 {
+    let payload = leb128_len(name.len() as u64) + name.len() as u64 + data_len;
+
+    1 + leb128_len(payload) + payload
+}
+
+/// returns the number of bytes of the unsigned LEB128 encoding of the value
+fn leb128_len(value: u64) -> u64 {
+    (64 - value.leading_zeros() as u64).max(1).div_ceil(7)
+}
+
+/// returns the custom sections in the module binary
+pub(crate) fn custom_sections(wasm: &[u8]) -> Vec<CustomSection> {
+    Parser::new(0)
+        .parse_all(wasm)
+        .filter_map(|payload| match payload {
+            Ok(Payload::CustomSection(reader)) => Some(CustomSection {
+                name: reader.name().to_string(),
+                size: (reader.name().len() + reader.data().len()) as u64,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// returns true if the custom section is removed by the strip options given
+fn is_stripped(strip: &[StripSection], name: &str) -> bool {
+    if strips(strip, StripSection::All) {
+        !name.starts_with("icp:") && name != INFO_SECTION_NAME
+    } else {
+        (strips(strip, StripSection::Names) && name == "name")
+            || (strips(strip, StripSection::Dwarf) && name.starts_with(".debug"))
+            || (strips(strip, StripSection::Producers) && name == "producers")
+            || (strips(strip, StripSection::TargetFeatures) && name == "target_features")
+    }
+}
+
+/// Remove the section groups from the module.
+///
+/// The sections removed are measured in the custom sections of the module binary emitted before the removal.
+///
+/// returns the sections removed and the number of bytes saved
+pub(crate) fn strip_sections(
+    module: &mut walrus::Module,
+    strip: &[StripSection],
+    sections: &[CustomSection],
+) -> StripReport {
+    if strips(strip, StripSection::Names) {
+        module.name = None;
+
+        for func in module.funcs.iter_mut() {
+            func.name = None;
+        }
+
+        let locals: Vec<walrus::LocalId> = module.locals.iter().map(|local| local.id()).collect();
+        for local in locals {
+            module.locals.get_mut(local).name = None;
+        }
+    }
+
+    if strips(strip, StripSection::Dwarf) {
+        module.debug = Default::default();
+    }
+
+    if strips(strip, StripSection::Producers) {
+        module.producers.clear();
+    }
+
+    let removed: Vec<String> = module
+        .customs
+        .iter()
+        .map(|(_, section)| section.name().to_string())
+        .filter(|name| is_stripped(strip, name))
+        .collect();
+
+    for name in removed {
+        module.customs.remove_raw(&name);
+    }
+
+    let removed: Vec<&CustomSection> = sections
+        .iter()
+        .filter(|section| is_stripped(strip, &section.name))
+        .collect();
+
+    StripReport {
+        sections: removed
+            .iter()
+            .map(|section| (section.name.clone(), section.size))
+            .collect(),
+        bytes_saved: removed.iter().map(|section| section.encoded_size()).sum(),
+    }
+}
@@ -0,0 +1,135 @@
+use walrus::{ir::Value, FunctionBuilder, FunctionId, ValType};
+
+use crate::options::StubMode;
+
+const IC0: &str = "ic0";
+const IC0_TRAP: &str = "trap";
+
+/// returns the `ic0::trap` import, it is added if missing,
+/// None if the module already imports it with a type other than `(address, address) -> ()`
+fn get_or_add_trap_import(
+    module: &mut walrus::Module,
+    address_type: ValType,
+) -> Option<FunctionId> {
+    if let Ok(trap) = module.imports.get_func(IC0, IC0_TRAP) {
+        let ty = module.types.get(module.funcs.get(trap).ty());
+
+        if ty.params() != [address_type, address_type] || !ty.results().is_empty() {
+            return None;
+        }
+
+        return Some(trap);
+    }
+
+    let ty = module.types.add(&[address_type, address_type], &[]);
+    let (trap, _) = module.add_import_func(IC0, IC0_TRAP, ty);
+
+    Some(trap)
+}
+
+/// Generate a local function with the type of the imported function `fn_id`,
+/// the function traps or returns the errno value when called depending on the stub mode.
+///
+/// returns the ID of the generated stub function and the stub mode actually used
+pub(crate) fn generate_stub(
+    module: &mut walrus::Module,
+    fn_id: FunctionId,
+    module_name: &str,
+    import_name: &str,
+    mode: &StubMode,
+) -> (FunctionId, StubMode) {
+    let ty = module.types.get(module.funcs.get(fn_id).ty());
+    let params = ty.params().to_vec();
+    let results = ty.results().to_vec();
+
+    let mut mode = *mode;
+
+    // errno can only be returned by the functions having a single i32 result
+    if let StubMode::Errno(_) = mode {
+        if results != [ValType::I32] {
+            log::warn!(
+                "Function {module_name}::{import_name} does not return errno, generating a trapping stub instead"
+            );
+            mode = StubMode::Trap;
+        }
+    }
+
+    // the trap message is copied from a passive data segment to the beginning of the memory,
+    // overwriting the memory is fine as the trap rolls back all the changes done
+    let memory = module
+        .memories
+        .iter()
+        .next()
+        .map(|memory| (memory.id(), memory.memory64));
+
+    if mode == StubMode::Trap && memory.is_none() {
+        log::warn!(
+            "Module has no memory to store the trap message for {module_name}::{import_name}, using unreachable instead"
+        );
+        mode = StubMode::Unreachable;
+    }
+
+    let trap = match (mode, memory) {
+        (StubMode::Trap, Some((_, memory64))) => {
+            let address_type = if memory64 { ValType::I64 } else { ValType::I32 };
+            let trap = get_or_add_trap_import(module, address_type);
+
+            if trap.is_none() {
+                log::warn!(
+                    "Module imports {IC0}::{IC0_TRAP} with an unexpected type, using unreachable for {module_name}::{import_name} instead"
+                );
+                mode = StubMode::Unreachable;
+            }
+
+            trap
+        }
+        _ => None,
+    };
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    builder.name(format!("__wasi2ic_stub_{module_name}_{import_name}"));
+
+    let mut body = builder.func_body();
+
+    match (mode, memory, trap) {
+        (StubMode::Errno(errno), _, _) => {
+            body.i32_const(errno);
+        }
+        (StubMode::Trap, Some((memory_id, memory64)), Some(trap)) => {
+            let message = format!("{module_name}::{import_name} not supported").into_bytes();
+            let length = message.len() as i32;
+
+            let data = module.data.add(walrus::DataKind::Passive, message);
+
+            let address = if memory64 {
+                Value::I64(0)
+            } else {
+                Value::I32(0)
+            };
+
+            let length_value = if memory64 {
+                Value::I64(length as i64)
+            } else {
+                Value::I32(length)
+            };
+
+            body.const_(address)
+                .i32_const(0)
+                .i32_const(length)
+                .memory_init(memory_id, data)
+                .const_(address)
+                .const_(length_value)
+                .call(trap)
+                .unreachable();
+        }
+        (StubMode::Trap, _, _) | (StubMode::Unreachable, _, _) => {
+            body.unreachable();
+        }
+    }
+
+    let args = params.iter().map(|ty| module.locals.add(*ty)).collect();
+
+    log::debug!("Generated {mode:?} stub for {module_name}::{import_name}");
+
+    (builder.finish(args, &mut module.funcs), mode)
+}
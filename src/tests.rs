@@ -1,160 +1,12 @@
 use std::collections::HashMap;
+use std::io::{Read, Write};
 
-use crate::*;
-
-#[test]
-fn test_add_start_entry() {
-    let wat = r#"
-        (module
-            (func $add (param i32 i32) (result i32)
-                local.get 0
-                local.get 1
-                i32.add
-            )
-
-            (func $_initialize
-                i32.const 2
-                i32.const 3
-                call $add
-                i32.const 5
-                i32.const 7
-                call $add
-                drop
-                drop
-            )
-        )
-    "#;
-
-    let binary = wat::parse_str(wat).unwrap();
-    let mut module = walrus::Module::from_buffer(&binary).unwrap();
-
-    assert!(module.start.is_none());
-
-    common::add_start_entry(&mut module);
-
-    assert!(module.start.is_some());
-}
-
-#[test]
-fn test_remove_start_export() {
-    let wat = r#"
-        (module
-            (func $add (param i32 i32) (result i32)
-                local.get 0
-                local.get 1
-                i32.add
-            )
-
-            (func $_initialize
-                i32.const 2
-                i32.const 3
-                call $add
-                i32.const 5
-                i32.const 7
-                call $add
-                drop
-                drop
-            )
-
-            (export "_initialize" (func $_initialize))
-        )
-    "#;
-
-    let binary = wat::parse_str(wat).unwrap();
-    let mut module = walrus::Module::from_buffer(&binary).unwrap();
-
-    let mut export_found: Option<walrus::ExportId> = None;
-
-    // try to find the initialize export
-    for export in module.exports.iter() {
-        if !export.name.starts_with("_initialize") {
-            continue;
-        }
-
-        if let walrus::ExportItem::Function(_) = export.item {
-            export_found = Some(export.id());
-        }
-    }
-
-    assert!(export_found.is_some());
-
-    common::remove_start_export(&mut module);
-
-    let mut export_found: Option<walrus::ExportId> = None;
-    // try to find the initialize export
-    for export in module.exports.iter() {
-        if !export.name.starts_with("_initialize") {
-            continue;
-        }
-
-        if let walrus::ExportItem::Function(_) = export.item {
-            export_found = Some(export.id());
-        }
-    }
-
-    assert!(export_found.is_none());
-}
-
-#[test]
-fn test_gather_replacement_ids() {
-    let wat = r#"
-    (module
-        (type (;0;) (func))
-        (type (;1;) (func (param i32)))
-        (type (;2;) (func (param i32 i32)))
-        (type (;3;) (func (param i32 i32) (result i32)))
-        (type (;4;) (func (param i32 i32 i32)))
-        (type (;5;) (func (param i32 i32 i32 i32) (result i32)))
-
-        (import "ic0" "debug_print" (func $_dprint (;0;) (type 2)))
-        (import "ic0" "msg_reply" (func $_msg_reply (;1;) (type 0)))
-        (import "wasi_unstable" "fd_write" (func $_wasi_unstable_fd_write (;2;) (type 5)))
-        (import "wasi_unstable" "random_get" (func $_wasi_unstable_random_get (;3;) (type 3)))
-        (import "wasi_unstable" "environ_get" (func $__imported_wasi_unstable_environ_get (;4;) (type 3)))
-        (import "wasi_unstable" "proc_exit" (func $__imported_wasi_unstable_proc_exit (;5;) (type 1)))
-
-        (func $_initialize (;6;) (type 0)
-            i32.const 1
-            i32.const 2
-            call $__ic_custom_random_get
-            i32.const 1
-            i32.const 2
-            call $_wasi_unstable_random_get
-            i32.const 4
-            i32.const 5
-            call $_wasi_unstable_fd_write
-            drop
-        )
-
-        (func $__ic_custom_random_get (;8;) (type 3) (param i32 i32) (result i32)
-            call $_msg_reply
-
-            i32.const 421
-        )
-
-        (func $ic_dummy_fd_write (;7;) (type 5) (param i32 i32 i32 i32) (result i32)
-            i32.const 0
-            i32.const 0
-            call $_dprint
-            i32.const 42
-        )
-
-        (export "__ic_custom_fd_write" (func $ic_dummy_fd_write))
-        (export "_initialize" (func $_initialize))
-    )
-    "#;
-
-    let binary = wat::parse_str(wat).unwrap();
-    let module = walrus::Module::from_buffer(&binary).unwrap();
-
-    let id_reps: HashMap<usize, usize> = common::gather_replacement_ids(&module)
-        .iter()
-        .map(|(x, y)| (x.index(), y.index()))
-        .collect();
+use wasi2ic::{
+    AdapterRule, Ic0ImportIssue, ImportMapping, ImportResolution, LimitViolation, LimitsProfile,
+    Options, PrefixResolver, ReplacementResolver, Signature, StubMode,
+};
 
-    assert!(id_reps[&2] == 8);
-    assert!(id_reps[&3] == 7);
-}
+use crate::*;
 
 #[test]
 fn test_do_module_replacements() {
@@ -250,7 +102,7 @@ fn test_do_module_replacements() {
     let binary = wat::parse_str(wat).unwrap();
     let mut module = walrus::Module::from_buffer(&binary).unwrap();
 
-    common::do_module_replacements(&mut module);
+    wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
 
     // we expect random_get and fd_write to be replaced, environ_get to be removed and the calls to the proc_exit to remain
     let imports = module.imports;
@@ -367,7 +219,7 @@ fn test_do_module_replacements_remaining_imports() {
     let binary = wat::parse_str(wat).unwrap();
     let mut module = walrus::Module::from_buffer(&binary).unwrap();
 
-    common::do_module_replacements(&mut module);
+    wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
 
     // we expect random_get and fd_write to be replaced, environ_get to be removed and the calls to the proc_exit to remain
     let imports = module.imports;
@@ -398,6 +250,7 @@ fn test_file_processing() {
         imports: false,
         input_file: "test/assets/main_test.wat".to_string(),
         output_file: "target/test/nowasi.wasm".to_string(),
+        ..Default::default()
     };
 
     let input_file = Path::new(&args.input_file);
@@ -437,6 +290,7 @@ fn test_bad_import() {
         imports: false,
         input_file: "test/assets/test_bad_imports.wat".to_string(),
         output_file: "target/test/nowasi1.wasm".to_string(),
+        ..Default::default()
     };
 
     let input_file = Path::new(&args.input_file);
@@ -446,9 +300,2091 @@ fn test_bad_import() {
     let _ = std::fs::remove_file(output_wasm);
     assert!(!output_wasm.exists());
 
-    let process_result = do_wasm_file_processing(&args);
+    let mut out = Vec::new();
+    let process_result = do_wasm_file_processing_with_output(&args, &mut out);
 
     assert!(output_wasm.exists());
+    assert!(String::from_utf8(out).unwrap().contains("Module imports:"));
+
+    let Err(wasi2ic::Wasi2icError::UnresolvedImports { imports }) = &process_result else {
+        panic!("expected unresolved imports, got {process_result:?}");
+    };
+    assert_eq!(
+        imports,
+        &vec![
+            ("env".to_string(), "some_function".to_string()),
+            (
+                "wasi_snapshot_preview1".to_string(),
+                "proc_exit".to_string()
+            ),
+        ]
+    );
+    assert_eq!(error_exit_code(&process_result.unwrap_err()), 14);
+    assert_eq!(
+        error_exit_code(&wasi2ic::Wasi2icError::Print(String::new())),
+        21
+    );
+}
+
+#[test]
+fn test_error_exit_codes() {
+    use wasi2ic::Wasi2icError;
+
+    let errors = [
+        Wasi2icError::Io(std::io::Error::other("io")),
+        Wasi2icError::Parse(String::new()),
+        Wasi2icError::InvalidOption(String::new()),
+        Wasi2icError::MappingFile(String::new()),
+        Wasi2icError::Mapping {
+            module: String::new(),
+            name: String::new(),
+            target: String::new(),
+            reason: String::new(),
+        },
+        Wasi2icError::UnresolvedImports { imports: vec![] },
+        Wasi2icError::InvalidIc0Imports { issues: vec![] },
+        Wasi2icError::InconsistentConversion {
+            version: String::new(),
+            imports: vec![],
+        },
+        Wasi2icError::CommandModule {
+            entry: String::new(),
+        },
+        Wasi2icError::Optimize(String::new()),
+        Wasi2icError::Print(String::new()),
+        Wasi2icError::LimitsExceeded { violations: vec![] },
+        Wasi2icError::BatchFailed {
+            failed: 0,
+            total: 0,
+        },
+    ];
+
+    let codes: std::collections::BTreeSet<u8> = errors.iter().map(error_exit_code).collect();
+
+    // every error kind has its own code, distinct from the check mode codes
+    assert_eq!(codes.len(), errors.len());
+    assert!(codes.iter().all(|code| *code >= 10));
+
+    let help = <arguments::Wasm2icArgs as clap::CommandFactory>::command()
+        .render_help()
+        .to_string();
+
+    for code in codes {
+        assert!(help.contains(&format!("  {code} - ")));
+    }
+}
+
+#[test]
+fn test_check_mode() {
+    let check = |input_file: &str| {
+        let args = arguments::Wasm2icArgs {
+            quiet: true,
+            check: true,
+            input_file: input_file.to_string(),
+            output_file: "target/test/check_not_written.wasm".to_string(),
+            ..Default::default()
+        };
+
+        do_wasm_file_check(&args)
+    };
+
+    assert_eq!(check("test/assets/main_test.wat"), CheckStatus::Convertible);
+    assert_eq!(
+        check("test/assets/test_bad_imports.wat"),
+        CheckStatus::Unresolved
+    );
+    assert_eq!(
+        check("test/assets/missing_file.wasm"),
+        CheckStatus::InvalidInput
+    );
+    assert!(!Path::new("target/test/check_not_written.wasm").exists());
+
+    // an already converted module needs no conversion
+    std::fs::create_dir_all("target/test").unwrap();
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        input_file: "test/assets/main_test.wat".to_string(),
+        output_file: "target/test/check_converted.wasm".to_string(),
+        ..Default::default()
+    };
+    do_wasm_file_processing(&args).unwrap();
+
+    assert_eq!(
+        check("target/test/check_converted.wasm"),
+        CheckStatus::Clean
+    );
+
+    assert_eq!(CheckStatus::Clean.exit_code(), 0);
+    assert_eq!(CheckStatus::Convertible.exit_code(), 3);
+    assert_eq!(CheckStatus::Unresolved.exit_code(), 4);
+    assert_eq!(CheckStatus::InvalidInput.exit_code(), 5);
+    assert_eq!(CheckStatus::LimitsExceeded.exit_code(), 6);
+}
+
+#[test]
+fn test_conversion_report() {
+    let wat = r#"
+    (module
+        (type (;0;) (func))
+        (type (;1;) (func (param i32)))
+        (type (;2;) (func (param i32 i32)))
+        (type (;3;) (func (param i32 i32) (result i32)))
+
+        (import "ic0" "msg_reply" (func $_msg_reply (;0;) (type 0)))
+        (import "wasi_snapshot_preview1" "random_get" (func $_random_get (;1;) (type 3)))
+        (import "wasi_snapshot_preview1" "environ_get" (func $_environ_get (;2;) (type 3)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $_proc_exit (;3;) (type 1)))
+        (import "wasi_snapshot_preview1" "sched_yield" (func $_sched_yield (;4;) (type 0)))
+
+        (func $_initialize (;5;) (type 0)
+            i32.const 1
+            i32.const 2
+            call $_random_get
+            i32.const 1
+            i32.const 2
+            call $_environ_get
+            drop
+            drop
+            i32.const 0
+            call $_proc_exit
+        )
+
+        (func $__ic_custom_random_get (;6;) (type 3) (param i32 i32) (result i32)
+            call $_msg_reply
+            i32.const 0
+        )
+
+        (func $__ic_custom_proc_exit (;7;) (type 2) (param i32 i32)
+        )
+
+        (func $ic_dummy_environ_get (;8;) (type 3) (param i32 i32) (result i32)
+            i32.const 0
+        )
+
+        (export "__ic_custom_environ_get" (func $ic_dummy_environ_get))
+        (export "__ic_custom_sched_yield" (func $_sched_yield))
+        (export "_initialize" (func $_initialize))
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
+
+    assert!(report.modified);
+    assert!(report.start_function_set);
+    assert_eq!(report.removed_exports, vec!["_initialize".to_string()]);
+
+    let resolution = |name: &str| {
+        report
+            .imports
+            .iter()
+            .find(|i| i.name == name)
+            .map(|i| i.resolution.clone())
+            .unwrap()
+    };
+
+    assert_eq!(resolution("msg_reply"), ImportResolution::Skipped);
+    assert_eq!(
+        resolution("random_get"),
+        ImportResolution::ReplacedByName {
+            replacement: "__ic_custom_random_get".to_string()
+        }
+    );
+    assert_eq!(
+        resolution("environ_get"),
+        ImportResolution::ReplacedByExport {
+            replacement: "__ic_custom_environ_get".to_string()
+        }
+    );
+    assert_eq!(
+        resolution("proc_exit"),
+        ImportResolution::TypeMismatch {
+            replacement: "__ic_custom_proc_exit".to_string(),
+            expected: Signature {
+                params: vec!["i32".to_string()],
+                results: vec![],
+            },
+            actual: Signature {
+                params: vec!["i32".to_string(), "i32".to_string()],
+                results: vec![],
+            },
+        }
+    );
+    assert_eq!(
+        resolution("sched_yield"),
+        ImportResolution::ReplacementIsImport {
+            replacement: "__ic_custom_sched_yield".to_string()
+        }
+    );
+
+    assert_eq!(report.replaced().count(), 2);
+    assert_eq!(report.unresolved().count(), 2);
+
+    // the stubbed and adapted imports are not unresolved
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Unreachable),
+        adapters: vec![AdapterRule::ConstParams(vec![0])],
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(
+        report.imports[3].resolution,
+        ImportResolution::ReplacedByAdapter {
+            replacement: "__ic_custom_proc_exit".to_string()
+        }
+    );
+    assert_eq!(
+        report.imports[4].resolution,
+        ImportResolution::Stubbed {
+            mode: StubMode::Unreachable
+        }
+    );
+    assert_eq!(report.replaced().count(), 4);
+    assert_eq!(report.unresolved().count(), 0);
+}
+
+#[test]
+fn test_custom_replacement_prefix() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32) (result i32)))
+
+        (import "wasi_unstable" "random_get" (func $_unstable_random_get (;0;) (type 0)))
+        (import "wasi_snapshot_preview1" "random_get" (func $_preview1_random_get (;1;) (type 0)))
+
+        (func $_initialize (;2;)
+            i32.const 1
+            i32.const 2
+            call $_unstable_random_get
+            i32.const 1
+            i32.const 2
+            call $_preview1_random_get
+            drop
+            drop
+        )
+
+        (func $my_wasi_unstable_random_get (;3;) (type 0) (param i32 i32) (result i32)
+            i32.const 0
+        )
+
+        (func $my_wasi_snapshot_preview1_random_get (;4;) (type 0) (param i32 i32) (result i32)
+            i32.const 0
+        )
+    )
+    "#;
+
+    let resolver = PrefixResolver::with_template("my_", "{prefix}{module}_{name}");
+    assert_eq!(
+        resolver.replacement_name("wasi_unstable", "fd_write"),
+        "my_wasi_unstable_fd_write"
+    );
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let options = Options {
+        resolver: std::sync::Arc::new(resolver),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.replaced().count(), 2);
+    assert!(module.imports.find("wasi_unstable", "random_get").is_none());
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "random_get")
+        .is_none());
+}
+
+#[test]
+fn test_import_mapping() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32)))
+        (type (;1;) (func (param i32 i64 i32) (result i32)))
+
+        (import "env" "some_function" (func $_some_function (;0;) (type 0)))
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $_clock_time_get (;1;) (type 1)))
+
+        (func $_initialize (;2;)
+            i32.const 1
+            i32.const 2
+            call $_some_function
+            i32.const 1
+            i64.const 2
+            i32.const 3
+            call $_clock_time_get
+            drop
+        )
+
+        (func $my_some_function (;3;) (type 0) (param i32 i32)
+        )
+
+        (func $clock_impl (;4;) (type 1) (param i32 i64 i32) (result i32)
+            i32.const 0
+        )
+
+        (export "my_clock" (func $clock_impl))
+    )
+    "#;
+
+    let toml_mapping = ImportMapping::from_toml(
+        r#"
+        [wasi_snapshot_preview1]
+        clock_time_get = "my_clock"
+
+        [env]
+        some_function = "my_some_function"
+        "#,
+    )
+    .unwrap();
+
+    let json_mapping = ImportMapping::from_json(
+        r#"{
+            "wasi_snapshot_preview1": { "clock_time_get": "my_clock" },
+            "env": { "some_function": "my_some_function" }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(toml_mapping, json_mapping);
+    assert_eq!(
+        toml_mapping.get("wasi_snapshot_preview1", "clock_time_get"),
+        Some("my_clock")
+    );
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let options = Options {
+        mapping: toml_mapping,
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.replaced().count(), 2);
+    assert!(module.imports.find("env", "some_function").is_none());
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "clock_time_get")
+        .is_none());
+
+    // mapping to a non-existing function
+    let mut mapping = ImportMapping::new();
+    mapping.insert("env", "some_function", "no_such_function");
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        mapping,
+        ..Default::default()
+    };
+    let result = wasi2ic::convert_module_with_options(&mut module, &options);
+    let Err(wasi2ic::Wasi2icError::Mapping {
+        module: import_module,
+        name,
+        target,
+        reason,
+    }) = result
+    else {
+        panic!("expected a mapping error, found {result:?}");
+    };
+    assert_eq!(
+        (import_module.as_str(), name.as_str(), target.as_str()),
+        ("env", "some_function", "no_such_function")
+    );
+    assert_eq!(reason, "does not exist");
+
+    // mapping to a function with a different type
+    let mut mapping = ImportMapping::new();
+    mapping.insert("env", "some_function", "my_clock");
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        mapping,
+        ..Default::default()
+    };
+    let result = wasi2ic::convert_module_with_options(&mut module, &options);
+    assert!(matches!(
+        result,
+        Err(wasi2ic::Wasi2icError::Mapping { ref target, ref reason, .. })
+            if target == "my_clock" && reason.contains("mismatching type")
+    ));
+}
+
+#[test]
+fn test_rewire_additional_modules() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32)))
+
+        (import "env" "some_function" (func $_some_function (;0;) (type 0)))
+
+        (func $_initialize (;1;)
+            i32.const 1
+            i32.const 2
+            call $_some_function
+        )
+
+        (func $__ic_custom_some_function (;2;) (type 0) (param i32 i32)
+        )
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+
+    // env is not rewired by default
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let report = wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
+
+    assert!(!report.modified);
+    assert_eq!(report.imports[0].resolution, ImportResolution::Skipped);
+    assert!(module.imports.find("env", "some_function").is_some());
+
+    // rewire env, when it is in the allowlist
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let mut options = Options::default();
+    options.modules.push("env".to_string());
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert!(report.modified);
+    assert!(report.imports[0].resolution.is_replaced());
+    assert!(module.imports.find("env", "some_function").is_none());
+}
+
+#[test]
+fn test_generate_stubs() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32) (result i32)))
+        (type (;1;) (func (param i32)))
+
+        (import "wasi_snapshot_preview1" "fd_renumber" (func $_fd_renumber (;0;) (type 0)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $_proc_exit (;1;) (type 1)))
+
+        (memory (export "memory") 1)
+
+        (func $_initialize (;2;)
+            i32.const 1
+            i32.const 2
+            call $_fd_renumber
+            call $_proc_exit
+        )
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+
+    // trap with the ic0::trap message
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Trap),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert!(report.modified);
+    assert_eq!(report.stubs.len(), 2);
+    assert_eq!(report.stubs[0].name, "fd_renumber");
+    assert_eq!(report.stubs[0].mode, StubMode::Trap);
+
+    let imports = wasi2ic::module_imports(&module);
+    assert_eq!(imports, vec![("ic0".to_string(), "trap".to_string())]);
+
+    let wasm = module.emit_wasm();
+    assert!(walrus::Module::from_buffer(&wasm).is_ok());
+
+    let wat = wasmprinter::print_bytes(&wasm).unwrap();
+    assert!(wat.contains("wasi_snapshot_preview1::fd_renumber not supported"));
+
+    // trap with unreachable
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Unreachable),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.stubs.len(), 2);
+    assert!(wasi2ic::module_imports(&module).is_empty());
+
+    // the 32-bit trap imported by a 64-bit memory module cannot be called with the 64-bit addresses
+    let binary = wat::parse_str(
+        r#"
+    (module
+        (import "ic0" "trap" (func $_trap (param i32 i32)))
+        (import "wasi_snapshot_preview1" "fd_renumber" (func $_fd_renumber (param i32 i32) (result i32)))
+
+        (memory (export "memory") i64 1)
+
+        (func $_initialize
+            i32.const 1
+            i32.const 2
+            call $_fd_renumber
+            drop
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Trap),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.stubs[0].mode, StubMode::Unreachable);
+    assert!(wasmparser::Validator::new()
+        .validate_all(&module.emit_wasm())
+        .is_ok());
+
+    // each of the imports sharing the same name gets its own stub
+    let binary = wat::parse_str(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_close" (func $_fd_close (param i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $_fd_close_again (param i32) (result i32)))
+
+        (func $_initialize
+            i32.const 1
+            call $_fd_close
+            i32.const 2
+            call $_fd_close_again
+            drop
+            drop
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Errno(52)),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.stubs.len(), 2);
+    assert!(wasi2ic::module_imports(&module).is_empty());
+
+    // the trap message names the import module
+    let binary = wat::parse_str(
+        r#"
+    (module
+        (import "env" "my_func" (func $_my_func))
+
+        (memory (export "memory") 1)
+
+        (func $_initialize
+            call $_my_func
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let mut options = Options {
+        stubs: Some(StubMode::Trap),
+        ..Default::default()
+    };
+    options.modules.push("env".to_string());
+
+    wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    let wat = wasmprinter::print_bytes(module.emit_wasm()).unwrap();
+    assert!(wat.contains("env::my_func not supported"));
+}
+
+#[test]
+fn test_generate_errno_stubs() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i64 i64 i32) (result i32)))
+        (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
+        (type (;2;) (func (param i32)))
+
+        (import "wasi_snapshot_preview1" "fd_advise" (func $_fd_advise (;0;) (type 0)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $_poll_oneoff (;1;) (type 1)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $_proc_exit (;2;) (type 2)))
+
+        (memory (export "memory") 1)
+
+        (func $_initialize (;3;)
+            i32.const 1
+            i64.const 2
+            i64.const 3
+            i32.const 4
+            call $_fd_advise
+            i32.const 1
+            i32.const 2
+            i32.const 3
+            call $_poll_oneoff
+            call $_proc_exit
+        )
+    )
+    "#;
+
+    assert_eq!("errno".parse::<StubMode>().unwrap(), StubMode::Errno(52));
+    assert_eq!("errno:8".parse::<StubMode>().unwrap(), StubMode::Errno(8));
+    assert_eq!("trap".parse::<StubMode>().unwrap(), StubMode::Trap);
+    assert!("errno:x".parse::<StubMode>().is_err());
+    assert!("ignore".parse::<StubMode>().is_err());
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let mut options = Options {
+        stubs: Some(StubMode::Errno(52)),
+        ..Default::default()
+    };
+    options
+        .import_stubs
+        .insert("poll_oneoff".to_string(), StubMode::Errno(58));
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    let modes: Vec<(&str, StubMode)> = report
+        .stubs
+        .iter()
+        .map(|stub| (stub.name.as_str(), stub.mode))
+        .collect();
+
+    // proc_exit does not return errno, a trapping stub is generated for it
+    assert_eq!(
+        modes,
+        vec![
+            ("fd_advise", StubMode::Errno(52)),
+            ("poll_oneoff", StubMode::Errno(58)),
+            ("proc_exit", StubMode::Trap)
+        ]
+    );
+
+    let wasm = module.emit_wasm();
+    assert!(walrus::Module::from_buffer(&wasm).is_ok());
+
+    let wat = wasmprinter::print_bytes(&wasm).unwrap();
+    assert!(wat.contains("i32.const 52"));
+    assert!(wat.contains("i32.const 58"));
+
+    // a bare import name only applies to the WASI modules, other modules need the qualified name
+    options
+        .import_stubs
+        .insert("env::poll_oneoff".to_string(), StubMode::Trap);
+    options.stubs = None;
+
+    assert_eq!(
+        options.stub_mode("wasi_snapshot_preview1", "poll_oneoff"),
+        Some(StubMode::Errno(58))
+    );
+    assert_eq!(
+        options.stub_mode("env", "poll_oneoff"),
+        Some(StubMode::Trap)
+    );
+    assert_eq!(options.stub_mode("env", "fd_advise"), None);
+    options
+        .import_stubs
+        .insert("fd_advise".to_string(), StubMode::Trap);
+    assert_eq!(options.stub_mode("env", "fd_advise"), None);
+}
+
+#[test]
+fn test_wasi_signature_validation() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32) (result i32)))
+
+        (import "wasi_snapshot_preview1" "fd_write" (func $_fd_write (;0;) (type 0)))
+
+        (func $_initialize (;1;)
+            i32.const 1
+            i32.const 2
+            call $_fd_write
+            drop
+        )
+
+        (func $__ic_custom_fd_write (;2;) (type 0) (param i32 i32) (result i32)
+            i32.const 0
+        )
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+
+    // the malformed import matches the wrongly typed replacement
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let report = wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
+
+    assert!(!report.modified);
+    assert_eq!(
+        report.imports[0].resolution,
+        ImportResolution::WasiSignatureMismatch {
+            function: "wasi_snapshot_preview1::fd_write".to_string(),
+            expected: Signature {
+                params: vec!["i32".to_string(); 4],
+                results: vec!["i32".to_string()],
+            },
+            actual: Signature {
+                params: vec!["i32".to_string(); 2],
+                results: vec!["i32".to_string()],
+            },
+        }
+    );
+    assert!(report.imports[0]
+        .resolution
+        .to_string()
+        .contains("expected (i32, i32, i32, i32) -> (i32), found (i32, i32) -> (i32)"));
+
+    // validation is disabled
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        validate_wasi_signatures: false,
+        ..Default::default()
+    };
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert!(report.modified);
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "fd_write")
+        .is_none());
+}
+
+#[test]
+fn test_generate_adapters() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i64 i32 i32) (result i32)))
+        (type (;1;) (func (param i32) (result i64)))
+
+        (import "wasi_snapshot_preview1" "fd_seek" (func $_fd_seek (;0;) (type 0)))
+        (import "env" "sum" (func $_sum (;1;) (type 1)))
+
+        (func $_initialize (;2;)
+            i32.const 1
+            i64.const 2
+            i32.const 3
+            i32.const 4
+            call $_fd_seek
+            drop
+            i32.const 5
+            call $_sum
+            drop
+        )
+
+        (func $__ic_custom_fd_seek (;3;) (param i32 i32 i32 i32 i32) (result i32 i32)
+            i32.const 0
+            i32.const 1
+        )
+
+        (func $__ic_custom_sum (;4;) (param i64 i32) (result i32)
+            i32.const 0
+        )
+    )
+    "#;
+
+    assert_eq!(
+        "const-params:7, 8".parse::<AdapterRule>().unwrap(),
+        AdapterRule::ConstParams(vec![7, 8])
+    );
+    assert!("widen".parse::<AdapterRule>().is_err());
+
+    let binary = wat::parse_str(wat).unwrap();
+
+    let mut options = Options::default();
+    options.modules.push("env".to_string());
+
+    // adapters are not generated by default
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+    assert_eq!(report.replaced().count(), 0);
+
+    // only some of the rules are given, the failed adapters leave no locals behind
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let locals = module.locals.iter().count();
+    options.adapters = vec![AdapterRule::SplitI64, AdapterRule::ConvertIntegers];
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+    assert_eq!(report.replaced().count(), 0);
+    assert_eq!(module.locals.iter().count(), locals);
+
+    // all rules needed are given
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    options.adapters = vec![
+        AdapterRule::SplitI64,
+        AdapterRule::DropResults,
+        AdapterRule::ConvertIntegers,
+        AdapterRule::ConstParams(vec![7]),
+    ];
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.replaced().count(), 2);
+    assert_eq!(
+        report.imports[0].resolution,
+        ImportResolution::ReplacedByAdapter {
+            replacement: "__ic_custom_fd_seek".to_string()
+        }
+    );
+    assert!(wasi2ic::module_imports(&module).is_empty());
+
+    let wasm = module.emit_wasm();
+    assert!(walrus::Module::from_buffer(&wasm).is_ok());
+
+    // each of the imports sharing the same name gets its own adapter
+    let binary = wat::parse_str(
+        r#"
+    (module
+        (import "env" "sum" (func $_sum (param i32) (result i64)))
+        (import "env" "sum" (func $_sum_again (param i32) (result i64)))
+
+        (func $_initialize
+            i32.const 1
+            call $_sum
+            i32.const 2
+            call $_sum_again
+            drop
+            drop
+        )
+
+        (func $__ic_custom_sum (param i64 i32) (result i32)
+            i32.const 0
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+
+    assert_eq!(report.replaced().count(), 2);
+    assert!(wasi2ic::module_imports(&module).is_empty());
+}
+
+#[test]
+fn test_json_output() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32) (result i32)))
+        (type (;1;) (func (param i32)))
+
+        (import "ic0" "debug_print" (func $_dprint (type 1)))
+        (import "wasi_snapshot_preview1" "random_get" (func $_random_get (type 0)))
+        (import "wasi_snapshot_preview1" "environ_get" (func $_environ_get (type 0)))
+
+        (func $__ic_custom_random_get (type 0)
+            i32.const 0
+        )
+
+        (func $_start
+            i32.const 0
+            i32.const 0
+            call $_random_get
+            i32.const 0
+            i32.const 0
+            call $_environ_get
+            drop
+            drop
+        )
+
+        (export "_start" (func $_start))
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let imports = wasi2ic::module_imports(&module);
+    let listing = output::imports_document("input.wasm", &imports);
+
+    assert_eq!(listing["version"], output::JSON_SCHEMA_VERSION);
+    assert_eq!(listing["input_file"], "input.wasm");
+    assert_eq!(listing["imports"].as_array().unwrap().len(), 3);
+    assert_eq!(listing["imports"][0]["module"], "ic0");
+    assert_eq!(listing["imports"][0]["name"], "debug_print");
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &Options::default()).unwrap();
+    let wasm = module.emit_wasm();
+    let module_check = ModuleCheck::new(&wasm, &arguments::Wasm2icArgs::default()).unwrap();
+
+    let document = output::conversion_document("input.wasm", "output.wasm", &report, &module_check);
+
+    assert_eq!(document["status"], "unresolved_imports");
+    assert_eq!(document["output_file"], "output.wasm");
+    assert_eq!(document["conversion"]["modified"], true);
+
+    let outcomes = document["conversion"]["imports"].as_array().unwrap();
+    assert_eq!(outcomes.len(), 3);
+    assert_eq!(outcomes[0]["resolution"]["kind"], "skipped");
+    assert_eq!(outcomes[1]["name"], "random_get");
+    assert_eq!(outcomes[1]["resolution"]["kind"], "replaced_by_name");
+    assert_eq!(outcomes[1]["resolution"]["replaced"], true);
+    assert_eq!(
+        outcomes[1]["resolution"]["replacement"],
+        "__ic_custom_random_get"
+    );
+    assert_eq!(outcomes[2]["resolution"]["kind"], "missing");
+    assert_eq!(outcomes[2]["resolution"]["replaced"], false);
+
+    assert_eq!(
+        document["remaining_imports"],
+        serde_json::json!([{ "module": "wasi_snapshot_preview1", "name": "environ_get" }])
+    );
+
+    // the stubbed imports are reported as replaced
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let options = Options {
+        stubs: Some(StubMode::Errno(52)),
+        ..Default::default()
+    };
+
+    let report = wasi2ic::convert_module_with_options(&mut module, &options).unwrap();
+    let wasm = module.emit_wasm();
+    let module_check = ModuleCheck::new(&wasm, &arguments::Wasm2icArgs::default()).unwrap();
+
+    let document = output::conversion_document("input.wasm", "output.wasm", &report, &module_check);
+
+    assert_eq!(document["status"], "ok");
+
+    let outcome = &document["conversion"]["imports"][2]["resolution"];
+    assert_eq!(outcome["kind"], "stubbed");
+    assert_eq!(outcome["replaced"], true);
+    assert_eq!(outcome["mode"], "errno:52");
+    assert_eq!(document["remaining_imports"], serde_json::json!([]));
+}
+
+#[test]
+fn test_ic0_import_check() {
+    let wat = r#"
+    (module
+        (type (;0;) (func (param i32 i32)))
+        (type (;1;) (func))
+        (type (;2;) (func (result i32)))
+        (type (;3;) (func (param i32 i32 i32)))
+
+        (import "ic0" "debug_print" (func $_dprint (type 0)))
+        (import "ic0" "msg_replay" (func $_msg_replay (type 1)))
+        (import "ic0" "msg_reply" (func $_msg_reply (type 2)))
+        (import "ic0" "stable_read" (func $_stable_read (type 3)))
+
+        (memory (;0;) 1)
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+
+    let issues = wasi2ic::check_bytes(&binary, &wasi2ic::CheckOptions::default())
+        .unwrap()
+        .ic0_issues;
+
+    assert_eq!(
+        issues,
+        vec![
+            Ic0ImportIssue::Unknown {
+                name: "msg_replay".to_string(),
+                strict: false,
+            },
+            Ic0ImportIssue::TypeMismatch {
+                name: "msg_reply".to_string(),
+                expected: Signature {
+                    params: vec![],
+                    results: vec![],
+                },
+                actual: Signature {
+                    params: vec![],
+                    results: vec!["i32".to_string()],
+                },
+            },
+            Ic0ImportIssue::Deprecated {
+                name: "stable_read".to_string(),
+                replacement: "stable64_read".to_string(),
+            },
+        ]
+    );
+
+    let wasm = module.emit_wasm();
+    let module_check = ModuleCheck::new(&wasm, &arguments::Wasm2icArgs::default()).unwrap();
+    assert!(!module_check.is_compatible());
+    assert!(module_check.remaining.is_empty());
+
+    // the signature mismatch is reported by its own check status, not as unresolved imports
+    std::fs::create_dir_all("target/test").unwrap();
+    std::fs::write("target/test/ic0_mismatch.wasm", &wasm).unwrap();
+    let status = do_wasm_file_check(&arguments::Wasm2icArgs {
+        quiet: true,
+        check: true,
+        input_file: "target/test/ic0_mismatch.wasm".to_string(),
+        ..Default::default()
+    });
+    assert_eq!(status, CheckStatus::InvalidIc0Imports);
+    assert_eq!(status.exit_code(), 7);
+
+    let args = arguments::Wasm2icArgs {
+        skip_ic0_check: true,
+        ..Default::default()
+    };
+    assert!(ModuleCheck::new(&wasm, &args).unwrap().is_compatible());
+
+    // unknown functions are only errors in the strict mode, type mismatches always are
+    assert!(!issues[0].is_error());
+    assert!(issues[1].is_error());
+    assert!(wasi2ic::check_bytes(
+        &binary,
+        &wasi2ic::CheckOptions {
+            strict_ic0: true,
+            ..Default::default()
+        }
+    )
+    .unwrap()
+    .ic0_issues[0]
+        .is_error());
+
+    let wasm = wat::parse_str(
+        r#"
+    (module
+        (import "ic0" "msg_replay" (func $_msg_replay))
+        (import "ic0" "env_var_count" (func $_env_var_count (result i32)))
+        (memory (;0;) 1)
+    )
+    "#,
+    )
+    .unwrap();
+
+    assert!(ModuleCheck::new(&wasm, &arguments::Wasm2icArgs::default())
+        .unwrap()
+        .is_compatible());
+
+    let args = arguments::Wasm2icArgs {
+        strict_ic0: true,
+        ..Default::default()
+    };
+    let module_check = ModuleCheck::new(&wasm, &args).unwrap();
+    assert!(!module_check.is_compatible());
+    assert_eq!(module_check.ic0_issues.len(), 1);
+
+    // addresses are 64-bit for the canisters using 64-bit memory
+    let wat = r#"
+    (module
+        (import "ic0" "debug_print" (func $_dprint (param i64 i64)))
+        (import "ic0" "trap" (func $_trap (param i32 i32)))
+
+        (memory (;0;) i64 1)
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+
+    let issues = wasi2ic::check_bytes(&binary, &wasi2ic::CheckOptions::default())
+        .unwrap()
+        .ic0_issues;
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].name(), "trap");
+    assert!(issues[0].is_error());
+}
+
+#[test]
+fn test_check_limits() {
+    let wat = r#"
+    (module
+        (global $g0 (mut i32) (i32.const 0))
+        (global $g1 (mut i32) (i32.const 1))
+        (global $g2 (mut i32) (i32.const 2))
+
+        (func $f0)
+        (func $f1)
+
+        (export "f0" (func $f0))
+        (export "f1" (func $f1))
+    )
+    "#;
+
+    let binary = wat::parse_str(wat).unwrap();
+    let mut module = walrus::Module::from_buffer(&binary).unwrap();
+    let wasm = module.emit_wasm();
+
+    let check_limits = |profile: &LimitsProfile| {
+        let options = wasi2ic::CheckOptions {
+            limits_profile: Some(profile.clone()),
+            ..Default::default()
+        };
+
+        wasi2ic::check_bytes(&wasm, &options)
+            .unwrap()
+            .limit_violations
+    };
+
+    let violations = check_limits(LimitsProfile::latest());
+    assert!(violations.is_empty());
+
+    let profile = LimitsProfile {
+        max_globals: 2,
+        max_exported_function_names_length: 3,
+        ..LimitsProfile::latest().clone()
+    };
+
+    let violations = check_limits(&profile);
+
+    assert_eq!(
+        violations,
+        vec![
+            LimitViolation {
+                limit: "globals",
+                measured: 3,
+                maximum: 2,
+            },
+            LimitViolation {
+                limit: "exported function names length",
+                measured: 4,
+                maximum: 3,
+            },
+        ]
+    );
+
+    // the limits are checked with the most recent profile unless skipped
+    let args = arguments::Wasm2icArgs::default();
+    assert_eq!(
+        args.limits_profile().unwrap(),
+        Some(LimitsProfile::latest())
+    );
+
+    let args = arguments::Wasm2icArgs {
+        limits_profile: Some("unknown".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        args.limits_profile(),
+        Err(wasi2ic::Wasi2icError::InvalidOption(_))
+    ));
+
+    let args = arguments::Wasm2icArgs {
+        skip_limits_check: true,
+        ..Default::default()
+    };
+    assert_eq!(args.limits_profile().unwrap(), None);
+}
+
+#[test]
+fn test_gzip_input_and_output() {
+    std::fs::create_dir_all("target/test").unwrap();
+
+    // gzip output chosen by the file extension
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        input_file: "test/assets/main_test.wat".to_string(),
+        output_file: "target/test/nowasi.wasm.gz".to_string(),
+        ..Default::default()
+    };
+    do_wasm_file_processing(&args).unwrap();
+
+    let compressed = std::fs::read(&args.output_file).unwrap();
+    assert!(compressed.starts_with(&[0x1f, 0x8b]));
+
+    // the compression is deterministic
+    do_wasm_file_processing(&args).unwrap();
+    assert_eq!(std::fs::read(&args.output_file).unwrap(), compressed);
+
+    // gzip output requested by the flag, the input compression is detected by the magic bytes
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        gzip: true,
+        input_file: "target/test/nowasi.wasm.gz".to_string(),
+        output_file: "target/test/nowasi_gzip.wasm".to_string(),
+        ..Default::default()
+    };
+    do_wasm_file_processing(&args).unwrap();
+
+    let compressed = std::fs::read(&args.output_file).unwrap();
+    assert!(compressed.starts_with(&[0x1f, 0x8b]));
+
+    let module = walrus::Module::from_buffer(&gunzip(&compressed)).unwrap();
+    assert!(module.imports.find("ic0", "debug_print").is_some());
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "fd_write")
+        .is_none());
+
+    // the Wasm text output cannot be compressed
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        gzip: true,
+        input_file: "test/assets/main_test.wat".to_string(),
+        output_file: "target/test/nowasi_gzip.wat".to_string(),
+        ..Default::default()
+    };
+    let _ = std::fs::remove_file(&args.output_file);
+    assert!(matches!(
+        do_wasm_file_processing(&args),
+        Err(wasi2ic::Wasi2icError::InvalidOption(_))
+    ));
+    assert!(!Path::new(&args.output_file).exists());
+}
+
+#[test]
+fn test_input_format_detection() {
+    std::fs::create_dir_all("target/test").unwrap();
+
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+    let wasm = wat::parse_bytes(&wat).unwrap().into_owned();
+
+    // the format is detected from the content regardless of the file extension
+    let inputs = [
+        ("target/test/format_text.wasm", wat.clone()),
+        ("target/test/format_binary.wat", wasm.clone()),
+        ("target/test/format_text_gzip", gzip(&wat).unwrap()),
+        ("target/test/format_binary_gzip", gzip(&wasm).unwrap()),
+    ];
+
+    for (input_file, content) in inputs {
+        std::fs::write(input_file, content).unwrap();
+
+        let imports = read_imports(input_file).unwrap();
+        assert!(imports.contains(&("ic0".to_string(), "debug_print".to_string())));
+    }
+
+    std::fs::write("target/test/format_invalid.wasm", "(module").unwrap();
+    assert!(read_imports("target/test/format_invalid.wasm").is_err());
+}
+
+#[test]
+fn test_batch_processing() {
+    assert_eq!(
+        batch::output_path(Path::new("target/a.wasm"), None, "_ic", false),
+        Path::new("target/a_ic.wasm")
+    );
+    assert_eq!(
+        batch::output_path(Path::new("target/a.wasm.gz"), Some("out"), "_ic", false),
+        Path::new("out/a_ic.wasm.gz")
+    );
+    assert_eq!(
+        batch::output_path(Path::new("a.wat"), Some("out"), "", false),
+        Path::new("out/a.wat")
+    );
+
+    // the compressed outputs get the .wasm.gz extension
+    assert_eq!(
+        batch::output_path(Path::new("target/a.wasm"), None, "_ic", true),
+        Path::new("target/a_ic.wasm.gz")
+    );
+    assert_eq!(
+        batch::output_path(Path::new("target/a.wasm.gz"), Some("out"), "", true),
+        Path::new("out/a.wasm.gz")
+    );
+    assert_eq!(
+        batch::output_path(Path::new("a.wat"), Some("out"), "", true),
+        Path::new("out/a.wasm.gz")
+    );
+
+    let inputs = batch::expand_inputs(&["test/assets".to_string()]).unwrap();
+    assert_eq!(
+        inputs,
+        vec![
+            Path::new("test/assets/main_test.wat"),
+            Path::new("test/assets/test_bad_imports.wat")
+        ]
+    );
+
+    let inputs = batch::expand_inputs(&[
+        "test/assets/main*.wat".to_string(),
+        "test/assets/main_test.wat".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(inputs, vec![Path::new("test/assets/main_test.wat")]);
+
+    assert!(batch::expand_inputs(&["test/assets/none*.wasm".to_string()]).is_err());
+
+    // the module with the bad imports fails, the other module is still converted
+    let _ = std::fs::remove_dir_all("target/test/batch");
+
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        out_dir: Some("target/test/batch".to_string()),
+        suffix: Some("_ic".to_string()),
+        files: vec!["test/assets".to_string()],
+        ..Default::default()
+    };
+    assert!(args.is_batch());
+
+    let mut out = Vec::new();
+    let result = batch::do_batch_processing(&args, &mut out);
+    assert!(matches!(
+        result,
+        Err(wasi2ic::Wasi2icError::BatchFailed {
+            failed: 1,
+            total: _
+        })
+    ));
+    assert!(String::from_utf8(out).unwrap().contains("failed"));
+
+    let imports = read_imports("target/test/batch/main_test_ic.wat").unwrap();
+    assert!(!imports.contains(&("wasi_snapshot_preview1".to_string(), "fd_write".to_string())));
+    assert!(Path::new("target/test/batch/test_bad_imports_ic.wat").exists());
+
+    let gzip_args = arguments::Wasm2icArgs {
+        gzip: true,
+        files: vec!["test/assets/main_test.wat".to_string()],
+        ..args.clone()
+    };
+    batch::do_batch_processing(&gzip_args, &mut Vec::new()).unwrap();
+
+    let compressed = std::fs::read("target/test/batch/main_test_ic.wasm.gz").unwrap();
+    walrus::Module::from_buffer(&gunzip(&compressed)).unwrap();
+
+    // the JSON output is a single document with the module documents nested
+    let args = arguments::Wasm2icArgs {
+        format: arguments::OutputFormat::Json,
+        ..args
+    };
+
+    let mut out = Vec::new();
+    assert!(batch::do_batch_processing(&args, &mut out).is_err());
+
+    let document: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    let modules = document["modules"].as_array().unwrap();
+    assert_eq!(modules.len(), 2);
+    assert_eq!(modules[0]["status"], "ok");
+    assert_eq!(modules[0]["document"]["status"], "ok");
+    assert_eq!(modules[1]["status"], "failed");
+
+    // inputs of the same name cannot be converted into the same output directory
+    for dir in ["target/test/batch_dup/d1", "target/test/batch_dup/d2"] {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::copy("test/assets/main_test.wat", format!("{dir}/m.wat")).unwrap();
+    }
+
+    let args = arguments::Wasm2icArgs {
+        out_dir: Some("target/test/batch_dup/out".to_string()),
+        files: vec![
+            "target/test/batch_dup/d1/m.wat".to_string(),
+            "target/test/batch_dup/d2/m.wat".to_string(),
+        ],
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    assert!(matches!(
+        batch::do_batch_processing(&args, &mut out),
+        Err(wasi2ic::Wasi2icError::InvalidOption(_))
+    ));
+    assert!(!Path::new("target/test/batch_dup/out/m.wat").exists());
+}
+
+#[test]
+fn test_resolve_files() {
+    let mut args = arguments::Wasm2icArgs {
+        files: vec!["input.wasm".to_string()],
+        ..Default::default()
+    };
+    args.resolve_files().unwrap();
+    assert_eq!(args.input_file, "input.wasm");
+    assert_eq!(args.output_file, "no_wasi.wasm");
+
+    args.files.push("output.wasm".to_string());
+    args.resolve_files().unwrap();
+    assert_eq!(args.output_file, "output.wasm");
+
+    args.files.push("other.wasm".to_string());
+    assert!(args.resolve_files().is_err());
+}
+
+/// returns the function body ranges relative to the start of the code section
+fn code_ranges(wasm: &[u8]) -> Vec<std::ops::Range<u64>> {
+    let mut code_start = 0;
+    let mut ranges = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload.unwrap() {
+            wasmparser::Payload::CodeSectionStart { range, .. } => code_start = range.start,
+            wasmparser::Payload::CodeSectionEntry(body) => {
+                let range = body.range();
+                ranges.push((range.start - code_start) as u64..(range.end - code_start) as u64);
+            }
+            _ => {}
+        }
+    }
+
+    ranges
+}
+
+/// returns the custom sections of the module with their names starting with `.debug`
+fn debug_sections(wasm: &[u8]) -> HashMap<String, Vec<u8>> {
+    wasmparser::Parser::new(0)
+        .parse_all(wasm)
+        .filter_map(|payload| match payload.unwrap() {
+            wasmparser::Payload::CustomSection(reader) if reader.name().starts_with(".debug") => {
+                Some((reader.name().to_string(), reader.data().to_vec()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// returns the module with a DWARF 4 line program attributing the given code address to the source line
+fn with_dwarf4_line(wasm: &[u8], address: u64, line: u64) -> Vec<u8> {
+    use gimli::write::{Address, Dwarf, EndianVec, LineProgram, LineString, Sections, Unit};
+
+    let encoding = gimli::Encoding {
+        format: gimli::Format::Dwarf32,
+        version: 4,
+        address_size: 4,
+    };
+
+    let mut program = LineProgram::new(
+        encoding,
+        gimli::LineEncoding::default(),
+        LineString::String(b"/src".to_vec()),
+        LineString::String(b"main.c".to_vec()),
+        None,
+    );
+    let directory = program.default_directory();
+    let file = program.add_file(LineString::String(b"main.c".to_vec()), directory, None);
+
+    program.begin_sequence(Some(Address::Constant(address)));
+    program.row().file = file;
+    program.row().line = line;
+    program.generate_row();
+    program.end_sequence(1);
+
+    let mut dwarf = Dwarf::new();
+    dwarf.units.add(Unit::new(encoding, program));
+
+    let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+    dwarf.write(&mut sections).unwrap();
+
+    let mut wasm = wasm.to_vec();
+    sections
+        .for_each(|id, data| {
+            let data = data.slice();
+            if data.is_empty() {
+                return Ok::<_, ()>(());
+            }
+
+            // custom section: id 0, size, name length, name, data
+            let name = id.name().as_bytes();
+            let mut content = Vec::new();
+            leb128_write(&mut content, name.len() as u64);
+            content.extend_from_slice(name);
+            content.extend_from_slice(data);
+
+            wasm.push(0);
+            leb128_write(&mut wasm, content.len() as u64);
+            wasm.extend(content);
+            Ok(())
+        })
+        .unwrap();
+
+    wasm
+}
+
+fn leb128_write(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// returns the addresses of the rows in the DWARF line programs
+fn line_rows(wasm: &[u8]) -> Vec<(u64, u64)> {
+    let sections = debug_sections(wasm);
+    let empty = Vec::new();
+
+    let dwarf = gimli::read::Dwarf::load(|id| {
+        Ok::<_, gimli::Error>(gimli::EndianSlice::new(
+            sections.get(id.name()).unwrap_or(&empty).as_slice(),
+            gimli::LittleEndian,
+        ))
+    })
+    .unwrap();
+
+    let mut rows = Vec::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().unwrap() {
+        let unit = dwarf.unit(header).unwrap();
+        let Some(program) = unit.line_program else {
+            continue;
+        };
+
+        let mut program_rows = program.rows();
+        while let Some((_, row)) = program_rows.next_row().unwrap() {
+            if let Some(line) = row.line() {
+                rows.push((row.address(), line.get()));
+            }
+        }
+    }
+
+    rows
+}
+
+#[test]
+fn test_keep_dwarf() {
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+    let wasm = wat::parse_bytes(&wat).unwrap().into_owned();
+
+    // attribute the last function ($_lambda) to line 83
+    let lambda = code_ranges(&wasm).last().unwrap().clone();
+    let wasm = with_dwarf4_line(&wasm, lambda.start, 83);
+    assert_eq!(line_rows(&wasm)[0], (lambda.start, 83));
+
+    // the debug sections are dropped by default
+    let output = wasi2ic::convert_bytes(&wasm, &wasi2ic::Options::default()).unwrap();
+    assert!(output.report.modified);
+    assert!(debug_sections(&output.wasm).is_empty());
+
+    let options = wasi2ic::Options {
+        keep_dwarf: true,
+        ..Default::default()
+    };
+    let output = wasi2ic::convert_bytes(&wasm, &options).unwrap();
+    assert!(output.report.modified);
+    assert!(debug_sections(&output.wasm).contains_key(".debug_line"));
+
+    // the line is attributed to the same function at its new address
+    let lambda = code_ranges(&output.wasm).last().unwrap().clone();
+    let rows = line_rows(&output.wasm);
+    assert!(
+        rows.iter()
+            .any(|(address, line)| *line == 83 && lambda.contains(address)),
+        "{rows:?} not in {lambda:?}"
+    );
+
+    // DWARF 5 line tables are rejected instead of being misread
+    let path = Path::new("test/assets/main_test.wat");
+    let wasm = wat::Parser::new()
+        .generate_dwarf(wat::GenerateDwarf::Lines)
+        .parse_str(Some(path), std::fs::read_to_string(path).unwrap())
+        .unwrap();
+    assert!(matches!(
+        wasi2ic::convert_bytes(&wasm, &options),
+        Err(wasi2ic::Wasi2icError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_strip_arguments() {
+    // "--strip" alone does not take the input file as its value
+    let args =
+        arguments::Wasm2icArgs::try_parse_from(["wasi2ic", "--strip", "in.wasm", "out.wasm"])
+            .unwrap();
+    assert_eq!(args.strip, vec![wasi2ic::StripSection::All]);
+    assert_eq!(args.files, vec!["in.wasm", "out.wasm"]);
+
+    let args = arguments::Wasm2icArgs::try_parse_from([
+        "wasi2ic",
+        "--strip=names,dwarf",
+        "in.wasm",
+        "out.wasm",
+    ])
+    .unwrap();
+    assert_eq!(
+        args.strip,
+        vec![wasi2ic::StripSection::Names, wasi2ic::StripSection::Dwarf]
+    );
+}
+
+#[test]
+fn test_embed_metadata() {
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+
+    let metadata: wasi2ic::Metadata = "private:git_commit=abc123".parse().unwrap();
+    assert_eq!(metadata.name, "git_commit");
+    assert_eq!(metadata.data, b"abc123");
+    assert_eq!(metadata.section_name(), "icp:private git_commit");
+
+    let candid: wasi2ic::Metadata = "candid:service=service : {}".parse().unwrap();
+    assert_eq!(candid.name, "candid:service");
+    assert_eq!(candid.visibility, wasi2ic::MetadataVisibility::Public);
+
+    assert!("git_commit".parse::<wasi2ic::Metadata>().is_err());
+    assert!("private:=abc".parse::<wasi2ic::Metadata>().is_err());
+
+    let options = wasi2ic::Options {
+        metadata: vec![
+            "candid:service=service : {}".parse().unwrap(),
+            "private:git_commit=abc123".parse().unwrap(),
+            // replaces the private section added before
+            "git_commit=def456".parse().unwrap(),
+        ],
+        strip: vec![wasi2ic::StripSection::All],
+        ..Default::default()
+    };
+
+    let output = wasi2ic::convert_bytes(&wat, &options).unwrap();
+    assert!(output.report.modified);
+    assert_eq!(
+        output.report.metadata,
+        vec![
+            ("icp:public candid:service".to_string(), 12),
+            ("icp:public git_commit".to_string(), 6),
+        ]
+    );
+
+    let module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    assert_eq!(
+        ic_wasm::metadata::list_metadata(&module),
+        vec!["icp:public candid:service", "icp:public git_commit"]
+    );
+    assert_eq!(
+        ic_wasm::metadata::get_metadata(&module, "git_commit").unwrap(),
+        b"def456".as_slice()
+    );
+
+    std::fs::create_dir_all("target/test").unwrap();
+    std::fs::write("target/test/service.did", "service : {}").unwrap();
+
+    let args = arguments::Wasm2icArgs {
+        candid: Some("target/test/service.did".to_string()),
+        metadata_file: vec!["private:candid:args=target/test/service.did"
+            .parse()
+            .unwrap()],
+        ..Default::default()
+    };
+    let options = args.conversion_options().unwrap();
+    assert_eq!(
+        options
+            .metadata
+            .iter()
+            .map(|m| (m.section_name(), m.data.as_slice()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "icp:public candid:service".to_string(),
+                b"service : {}".as_slice()
+            ),
+            (
+                "icp:private candid:args".to_string(),
+                b"service : {}".as_slice()
+            ),
+        ]
+    );
+
+    let file: arguments::MetadataFile = "private:candid:args=target/test/service.did"
+        .parse()
+        .unwrap();
+    assert_eq!(file.name, "candid:args");
+    assert_eq!(file.path, std::path::Path::new("target/test/service.did"));
+    assert_eq!(file.visibility, wasi2ic::MetadataVisibility::Private);
+    assert!("candid:args=".parse::<arguments::MetadataFile>().is_err());
+
+    // the missing file is named in the error
+    for args in [
+        arguments::Wasm2icArgs {
+            candid: Some("target/test/missing.did".to_string()),
+            ..Default::default()
+        },
+        arguments::Wasm2icArgs {
+            metadata_file: vec!["candid:args=target/test/missing.did".parse().unwrap()],
+            ..Default::default()
+        },
+    ] {
+        let Err(error) = args.conversion_options() else {
+            panic!("expected the missing file to fail");
+        };
+        assert!(matches!(error, wasi2ic::Wasi2icError::Io(_)));
+        assert!(error.to_string().contains("target/test/missing.did"));
+    }
+}
+
+#[test]
+fn test_already_converted_post_processing() {
+    std::fs::create_dir_all("target/test").unwrap();
+    std::fs::write("target/test/converted_service.did", "service : {}").unwrap();
+
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        input_file: "test/assets/main_test.wat".to_string(),
+        output_file: "target/test/converted_once.wasm".to_string(),
+        ..Default::default()
+    };
+    do_wasm_file_processing(&args).unwrap();
+
+    // the imports are not rewired again, but the metadata is still embedded
+    let args = arguments::Wasm2icArgs {
+        quiet: true,
+        candid: Some("target/test/converted_service.did".to_string()),
+        input_file: "target/test/converted_once.wasm".to_string(),
+        output_file: "target/test/converted_candid.wasm".to_string(),
+        ..Default::default()
+    };
+    do_wasm_file_processing(&args).unwrap();
+
+    let converted = std::fs::read("target/test/converted_once.wasm").unwrap();
+    let with_candid = std::fs::read("target/test/converted_candid.wasm").unwrap();
+    assert_ne!(converted, with_candid);
+
+    let module = walrus::Module::from_buffer(&with_candid).unwrap();
+    assert_eq!(
+        ic_wasm::metadata::get_metadata(&module, "candid:service").unwrap(),
+        b"service : {}".as_slice()
+    );
+
+    let options = wasi2ic::Options {
+        optimize: Some(wasi2ic::OptimizeLevel::Shrink),
+        ..Default::default()
+    };
+    let output = wasi2ic::convert_bytes(&with_candid, &options).unwrap();
+    assert!(output.report.modified);
+    assert!(output.report.imports.is_empty());
+    assert!(output.report.previous_conversion.is_some());
+    assert!(output.report.optimized.is_some());
+
+    // the conversion record survives the optimization
+    let again = wasi2ic::convert_bytes(&output.wasm, &wasi2ic::Options::default()).unwrap();
+    assert!(again.report.previous_conversion.is_some());
+    assert!(!again.report.modified);
+}
+
+#[test]
+fn test_init_hooks() {
+    assert_eq!(
+        "hooks".parse::<wasi2ic::InitMode>().unwrap(),
+        wasi2ic::InitMode::Hooks
+    );
+    assert_eq!(wasi2ic::InitMode::Start.to_string(), "start");
+    assert!("ctor".parse::<wasi2ic::InitMode>().is_err());
+
+    // the functions called by the exported function, in order
+    let calls = |module: &walrus::Module, export: &str| -> Vec<String> {
+        let id = module.exports.get_func(export).unwrap();
+        let walrus::FunctionKind::Local(func) = &module.funcs.get(id).kind else {
+            panic!("{export} is not a local function");
+        };
+
+        func.block(func.entry_block())
+            .instrs
+            .iter()
+            .filter_map(|(instr, _)| match instr {
+                walrus::ir::Instr::Call(call) => module.funcs.get(call.func).name.clone(),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let mut wat = std::fs::read_to_string("test/assets/main_test.wat").unwrap();
+    let end = wat.rfind(')').unwrap();
+    wat.insert_str(
+        end,
+        r#"(func $init (export "canister_init") (call $_msg_reply))
+"#,
+    );
+
+    let options = wasi2ic::Options {
+        init_mode: wasi2ic::InitMode::Hooks,
+        ..Default::default()
+    };
+
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap();
+    assert!(!output.report.start_function_set);
+    assert_eq!(
+        output.report.init_hooks,
+        vec![
+            wasi2ic::InitHookReport {
+                export: wasi2ic::CANISTER_INIT.to_string(),
+                synthesized: false,
+            },
+            wasi2ic::InitHookReport {
+                export: wasi2ic::CANISTER_POST_UPGRADE.to_string(),
+                synthesized: true,
+            },
+        ]
+    );
+
+    let module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    assert!(module.start.is_none());
+    assert!(module.exports.get_func("_initialize").is_err());
+    assert_eq!(calls(&module, "canister_init"), vec!["_initialize", "init"]);
+    assert_eq!(calls(&module, "canister_post_upgrade"), vec!["_initialize"]);
+
+    // the start function is used by default
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &wasi2ic::Options::default()).unwrap();
+    assert!(output.report.start_function_set);
+    assert!(output.report.init_hooks.is_empty());
+}
+
+#[test]
+fn test_chain_start_function() {
+    assert_eq!(
+        "start-first".parse::<wasi2ic::StartOrder>().unwrap(),
+        wasi2ic::StartOrder::StartFirst
+    );
+    assert_eq!(
+        wasi2ic::StartOrder::InitializeFirst.to_string(),
+        "initialize-first"
+    );
+    assert!("last".parse::<wasi2ic::StartOrder>().is_err());
+
+    let mut wat = std::fs::read_to_string("test/assets/main_test.wat").unwrap();
+    let end = wat.rfind(')').unwrap();
+    wat.insert_str(
+        end,
+        r#"(func $ctors (call $_msg_reply))
+(start $ctors)
+"#,
+    );
+
+    // the functions called by the start function, in order
+    let start_calls = |wasm: &[u8]| -> Vec<String> {
+        let module = walrus::Module::from_buffer(wasm).unwrap();
+        let walrus::FunctionKind::Local(func) = &module.funcs.get(module.start.unwrap()).kind
+        else {
+            panic!("start is not a local function");
+        };
+
+        func.block(func.entry_block())
+            .instrs
+            .iter()
+            .filter_map(|(instr, _)| match instr {
+                walrus::ir::Instr::Call(call) => module.funcs.get(call.func).name.clone(),
+                _ => None,
+            })
+            .collect()
+    };
+
+    for (order, calls) in [
+        (
+            wasi2ic::StartOrder::InitializeFirst,
+            ["_initialize", "ctors"],
+        ),
+        (wasi2ic::StartOrder::StartFirst, ["ctors", "_initialize"]),
+    ] {
+        let options = wasi2ic::Options {
+            start_order: order,
+            ..Default::default()
+        };
+
+        let output = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap();
+        assert!(output.report.start_function_set);
+        assert_eq!(
+            output.report.chained_start,
+            Some(wasi2ic::ChainedStartReport {
+                original: "ctors".to_string(),
+                order,
+            })
+        );
+        assert_eq!(start_calls(&output.wasm), calls);
+    }
+
+    // nothing to chain without an existing start function
+    let wat = std::fs::read("test/assets/main_test.wat").unwrap();
+    let output = wasi2ic::convert_bytes(&wat, &wasi2ic::Options::default()).unwrap();
+    assert!(output.report.start_function_set);
+    assert!(output.report.chained_start.is_none());
+}
+
+#[test]
+fn test_initializer_candidates() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+
+            (func $__wasm_call_ctors
+                i32.const 0
+                i32.const 0
+                call $random_get
+                drop
+            )
+
+            (func $__ic_custom_random_get (param i32 i32) (result i32)
+                i32.const 0
+            )
+
+            (func $_initialize_tables)
+
+            (export "__wasm_call_ctors" (func $__wasm_call_ctors))
+            (export "_initialize_tables" (func $_initialize_tables))
+        )
+    "#;
+
+    // no _initialize function, nothing is called
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &wasi2ic::Options::default()).unwrap();
+    assert!(output.report.modified);
+    assert!(output.report.initializer.is_none());
+    assert!(!output.report.start_function_set);
+
+    let options = wasi2ic::Options {
+        initializers: vec![
+            wasi2ic::INITIALIZE.to_string(),
+            "__wasm_call_ctors".to_string(),
+        ],
+        ..Default::default()
+    };
+
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap();
+    assert_eq!(
+        output.report.initializer.as_deref(),
+        Some("__wasm_call_ctors")
+    );
+    assert!(output.report.start_function_set);
+    assert_eq!(output.report.removed_exports, vec!["__wasm_call_ctors"]);
+
+    // only the initializer export is removed, not the other exports sharing its prefix
+    let module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    assert!(module.exports.get_func("_initialize_tables").is_ok());
+
+    let args = arguments::Wasm2icArgs::default();
+    assert_eq!(
+        args.conversion_options().unwrap().initializers,
+        vec!["_initialize"]
+    );
+
+    // candidates taking parameters or returning results cannot be called as the initializer
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+
+            (func $_initialize (param i32)
+                local.get 0
+                i32.const 0
+                call $random_get
+                drop
+            )
+
+            (func $main (param i32 i32) (result i32)
+                i32.const 0
+            )
+
+            (func $__ic_custom_random_get (param i32 i32) (result i32)
+                i32.const 0
+            )
+
+            (export "_initialize" (func $_initialize))
+            (export "main" (func $main))
+        )
+    "#;
+
+    // the WASI initializer of a wrong type is skipped
+    for init_mode in [wasi2ic::InitMode::Start, wasi2ic::InitMode::Hooks] {
+        let options = wasi2ic::Options {
+            init_mode,
+            ..Default::default()
+        };
+
+        let output = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap();
+        assert!(output.report.modified);
+        assert!(output.report.initializer.is_none());
+        assert!(!output.report.start_function_set);
+        assert!(output.report.init_hooks.is_empty());
+        assert!(wasmparser::Validator::new()
+            .validate_all(&output.wasm)
+            .is_ok());
+    }
+
+    // an explicitly named initializer of a wrong type is rejected
+    let options = wasi2ic::Options {
+        initializers: vec!["main".to_string()],
+        ..Default::default()
+    };
+
+    let error = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap_err();
+    assert!(matches!(&error, wasi2ic::Wasi2icError::InvalidOption(_)));
+    assert!(error.to_string().contains("(i32, i32) -> (i32)"));
+}
+
+#[test]
+fn test_command_module() {
+    assert_eq!(
+        "init".parse::<wasi2ic::CommandMode>().unwrap(),
+        wasi2ic::CommandMode::Init
+    );
+    assert_eq!(wasi2ic::CommandMode::Reject.to_string(), "reject");
+    assert!("run".parse::<wasi2ic::CommandMode>().is_err());
+
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+            (func $_start
+                i32.const 1
+                call $proc_exit
+            )
+
+            (memory 1)
+            (export "memory" (memory 0))
+            (export "_start" (func $_start))
+        )
+    "#;
+
+    // commands are converted like other modules by default, leaving proc_exit unresolved
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &wasi2ic::Options::default()).unwrap();
+    assert!(!output.report.modified);
+    assert!(output.report.initializer.is_none());
+
+    let options = wasi2ic::Options {
+        command: Some(wasi2ic::CommandMode::Reject),
+        ..Default::default()
+    };
+
+    let error = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap_err();
+    assert!(matches!(
+        &error,
+        wasi2ic::Wasi2icError::CommandModule { entry } if entry == "_start"
+    ));
+
+    let args = arguments::Wasm2icArgs {
+        command: Some(wasi2ic::CommandMode::Reject),
+        ..Default::default()
+    };
+    let mut module = walrus::Module::from_buffer(&wat::parse_str(wat).unwrap()).unwrap();
+    let error =
+        wasi2ic::convert_module_with_options(&mut module, &args.conversion_options().unwrap())
+            .unwrap_err();
+    assert_eq!(error_exit_code(&error), 20);
+
+    let options = wasi2ic::Options {
+        command: Some(wasi2ic::CommandMode::Init),
+        ..Default::default()
+    };
+
+    let output = wasi2ic::convert_bytes(wat.as_bytes(), &options).unwrap();
+    assert_eq!(output.report.initializer.as_deref(), Some(wasi2ic::START));
+    assert!(output.report.start_function_set);
+    assert_eq!(output.report.removed_exports, vec!["_start"]);
+    assert_eq!(
+        output
+            .report
+            .stubs
+            .iter()
+            .map(|stub| (stub.name.as_str(), stub.mode))
+            .collect::<Vec<_>>(),
+        vec![("proc_exit", wasi2ic::StubMode::Trap)]
+    );
+
+    let module = walrus::Module::from_buffer(&output.wasm).unwrap();
+    assert!(module.exports.get_func("_start").is_err());
+    assert!(module
+        .imports
+        .find("wasi_snapshot_preview1", "proc_exit")
+        .is_none());
+}
+
+fn gzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
 
-    assert!(process_result.is_err());
+fn gunzip(bytes: &[u8]) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    decompressed
 }
@@ -0,0 +1,89 @@
+use walrus::ValType::{self, I32, I64};
+
+use crate::common::{WASI_SNAPSHOT_PREVIEW1, WASI_UNSTABLE};
+use crate::report::Signature;
+
+type WasiFunction = (&'static str, &'static [ValType], &'static [ValType]);
+
+/// Canonical core Wasm signatures of the `wasi_snapshot_preview1` functions.
+const WASI_SNAPSHOT_PREVIEW1_FUNCTIONS: &[WasiFunction] = &[
+    ("args_get", &[I32, I32], &[I32]),
+    ("args_sizes_get", &[I32, I32], &[I32]),
+    ("environ_get", &[I32, I32], &[I32]),
+    ("environ_sizes_get", &[I32, I32], &[I32]),
+    ("clock_res_get", &[I32, I32], &[I32]),
+    ("clock_time_get", &[I32, I64, I32], &[I32]),
+    ("fd_advise", &[I32, I64, I64, I32], &[I32]),
+    ("fd_allocate", &[I32, I64, I64], &[I32]),
+    ("fd_close", &[I32], &[I32]),
+    ("fd_datasync", &[I32], &[I32]),
+    ("fd_fdstat_get", &[I32, I32], &[I32]),
+    ("fd_fdstat_set_flags", &[I32, I32], &[I32]),
+    ("fd_fdstat_set_rights", &[I32, I64, I64], &[I32]),
+    ("fd_filestat_get", &[I32, I32], &[I32]),
+    ("fd_filestat_set_size", &[I32, I64], &[I32]),
+    ("fd_filestat_set_times", &[I32, I64, I64, I32], &[I32]),
+    ("fd_pread", &[I32, I32, I32, I64, I32], &[I32]),
+    ("fd_prestat_get", &[I32, I32], &[I32]),
+    ("fd_prestat_dir_name", &[I32, I32, I32], &[I32]),
+    ("fd_pwrite", &[I32, I32, I32, I64, I32], &[I32]),
+    ("fd_read", &[I32, I32, I32, I32], &[I32]),
+    ("fd_readdir", &[I32, I32, I32, I64, I32], &[I32]),
+    ("fd_renumber", &[I32, I32], &[I32]),
+    ("fd_seek", &[I32, I64, I32, I32], &[I32]),
+    ("fd_sync", &[I32], &[I32]),
+    ("fd_tell", &[I32, I32], &[I32]),
+    ("fd_write", &[I32, I32, I32, I32], &[I32]),
+    ("path_create_directory", &[I32, I32, I32], &[I32]),
+    ("path_filestat_get", &[I32, I32, I32, I32, I32], &[I32]),
+    (
+        "path_filestat_set_times",
+        &[I32, I32, I32, I32, I64, I64, I32],
+        &[I32],
+    ),
+    ("path_link", &[I32, I32, I32, I32, I32, I32, I32], &[I32]),
+    (
+        "path_open",
+        &[I32, I32, I32, I32, I32, I64, I64, I32, I32],
+        &[I32],
+    ),
+    ("path_readlink", &[I32, I32, I32, I32, I32, I32], &[I32]),
+    ("path_remove_directory", &[I32, I32, I32], &[I32]),
+    ("path_rename", &[I32, I32, I32, I32, I32, I32], &[I32]),
+    ("path_symlink", &[I32, I32, I32, I32, I32], &[I32]),
+    ("path_unlink_file", &[I32, I32, I32], &[I32]),
+    ("poll_oneoff", &[I32, I32, I32, I32], &[I32]),
+    ("proc_exit", &[I32], &[]),
+    ("proc_raise", &[I32], &[I32]),
+    ("sched_yield", &[], &[I32]),
+    ("random_get", &[I32, I32], &[I32]),
+    ("sock_accept", &[I32, I32, I32], &[I32]),
+    ("sock_recv", &[I32, I32, I32, I32, I32, I32], &[I32]),
+    ("sock_send", &[I32, I32, I32, I32, I32], &[I32]),
+    ("sock_shutdown", &[I32, I32], &[I32]),
+];
+
+/// Functions of `wasi_snapshot_preview1` not available in `wasi_unstable`,
+/// the signatures of all other functions are the same in both modules.
+const WASI_UNSTABLE_MISSING_FUNCTIONS: &[&str] = &["sock_accept"];
+
+/// returns the canonical signature of the WASI function, or None if the function is not known
+pub(crate) fn wasi_signature(module_name: &str, import_name: &str) -> Option<Signature> {
+    let known = match module_name {
+        WASI_SNAPSHOT_PREVIEW1 => true,
+        WASI_UNSTABLE => !WASI_UNSTABLE_MISSING_FUNCTIONS.contains(&import_name),
+        _ => false,
+    };
+
+    if !known {
+        return None;
+    }
+
+    WASI_SNAPSHOT_PREVIEW1_FUNCTIONS
+        .iter()
+        .find(|(name, _, _)| *name == import_name)
+        .map(|(_, params, results)| Signature {
+            params: params.iter().map(|t| t.to_string()).collect(),
+            results: results.iter().map(|t| t.to_string()).collect(),
+        })
+}